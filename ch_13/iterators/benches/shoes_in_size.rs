@@ -0,0 +1,31 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use iterators::{par_shoes_in_size, shoes_in_size, Shoe};
+
+const SHOES: u32 = 1_000_000;
+const STYLES: [&str; 4] = ["Sneaker", "Sandal", "Boot", "Loafer"];
+
+fn catalogue() -> Vec<Shoe> {
+    (0..SHOES)
+        .map(|i| Shoe {
+            size: 5 + i % 10,
+            style: STYLES[i as usize % STYLES.len()].to_string(),
+        })
+        .collect()
+}
+
+fn filter_by_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("shoes_in_size, 1M shoes");
+    group.sample_size(20);
+
+    group.bench_function("sequential", |b| {
+        b.iter_batched(catalogue, |shoes| shoes_in_size(shoes, 10), BatchSize::LargeInput)
+    });
+    group.bench_function("parallel", |b| {
+        b.iter_batched(catalogue, |shoes| par_shoes_in_size(shoes, 10), BatchSize::LargeInput)
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, filter_by_size);
+criterion_main!(benches);
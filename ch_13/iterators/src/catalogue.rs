@@ -0,0 +1,196 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+
+use crate::Shoe;
+
+// A catalogue row that didn't make a valid `Shoe`. Rows are counted from 1,
+// not counting the CSV header.
+#[derive(Debug, PartialEq)]
+pub struct RowError {
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum CatalogueError {
+    Io(io::Error),
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    MissingColumn(&'static str),
+    // every bad row in the catalogue, not just the first one
+    Rows(Vec<RowError>),
+}
+
+impl fmt::Display for CatalogueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CatalogueError::Io(err) => write!(f, "{}", err),
+            CatalogueError::Csv(err) => write!(f, "{}", err),
+            CatalogueError::Json(err) => write!(f, "{}", err),
+            CatalogueError::MissingColumn(column) => write!(f, "header has no `{}` column", column),
+            CatalogueError::Rows(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "row {}: {}", err.row, err.message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for CatalogueError {}
+
+impl From<io::Error> for CatalogueError {
+    fn from(err: io::Error) -> CatalogueError {
+        CatalogueError::Io(err)
+    }
+}
+
+impl From<csv::Error> for CatalogueError {
+    fn from(err: csv::Error) -> CatalogueError {
+        CatalogueError::Csv(err)
+    }
+}
+
+impl From<serde_json::Error> for CatalogueError {
+    fn from(err: serde_json::Error) -> CatalogueError {
+        CatalogueError::Json(err)
+    }
+}
+
+// A row as it appears in the file. Both fields are read as text so that a
+// bad value is reported against its row instead of failing the whole file.
+#[derive(Deserialize)]
+struct RawShoe {
+    #[serde(default, deserialize_with = "scalar_as_text")]
+    size: Option<String>,
+    #[serde(default, deserialize_with = "scalar_as_text")]
+    style: Option<String>,
+}
+
+impl RawShoe {
+    fn into_shoe(self) -> Result<Shoe, String> {
+        let size = match self.size.as_deref().map(str::trim) {
+            None | Some("") => return Err(String::from("missing size")),
+            Some(size) => match size.parse() {
+                Ok(size) => size,
+                Err(_) => return Err(format!("invalid size `{}`", size)),
+            },
+        };
+
+        let style = match self.style {
+            Some(style) if !style.trim().is_empty() => style,
+            _ => return Err(String::from("missing style")),
+        };
+
+        Ok(Shoe { size, style })
+    }
+}
+
+// CSV has only text while JSON sizes are usually numbers; take either
+fn scalar_as_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error> where D: Deserializer<'de> {
+    struct ScalarVisitor;
+
+    impl<'de> Visitor<'de> for ScalarVisitor {
+        type Value = Option<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a string or a number")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Option<String>, E> {
+            Ok(Some(String::from(v)))
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Option<String>, E> {
+            Ok(Some(v.to_string()))
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Option<String>, E> {
+            Ok(Some(v.to_string()))
+        }
+
+        fn visit_f64<E: de::Error>(self, v: f64) -> Result<Option<String>, E> {
+            Ok(Some(v.to_string()))
+        }
+
+        fn visit_bool<E: de::Error>(self, v: bool) -> Result<Option<String>, E> {
+            Ok(Some(v.to_string()))
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Option<String>, E> {
+            Ok(None)
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Option<String>, E> {
+            Ok(None)
+        }
+    }
+
+    deserializer.deserialize_any(ScalarVisitor)
+}
+
+fn collect_rows<I>(rows: I) -> Result<Vec<Shoe>, CatalogueError> where I: Iterator<Item = Result<Shoe, String>> {
+    let mut shoes = vec![];
+    let mut errors = vec![];
+
+    for (i, row) in rows.enumerate() {
+        match row {
+            Ok(shoe) => shoes.push(shoe),
+            Err(message) => errors.push(RowError { row: i + 1, message }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(shoes)
+    } else {
+        Err(CatalogueError::Rows(errors))
+    }
+}
+
+// Expects a header row with `size` and `style` columns, in any order.
+pub fn load_csv<R: Read>(reader: R) -> Result<Vec<Shoe>, CatalogueError> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).flexible(true).from_reader(reader);
+
+    let headers = reader.headers()?.clone();
+    for column in ["size", "style"] {
+        if !headers.iter().any(|h| h == column) {
+            return Err(CatalogueError::MissingColumn(column));
+        }
+    }
+
+    collect_rows(reader.deserialize::<RawShoe>().map(|row| row.map_err(|err| err.to_string())?.into_shoe()))
+}
+
+// Expects an array of `{ "size": 10, "style": "Boot" }` objects.
+pub fn load_json<R: Read>(reader: R) -> Result<Vec<Shoe>, CatalogueError> {
+    let entries: Vec<serde_json::Value> = serde_json::from_reader(reader)?;
+
+    collect_rows(entries.into_iter().map(|entry| {
+        if !entry.is_object() {
+            return Err(String::from("expected an object"));
+        }
+        RawShoe::deserialize(entry).map_err(|err| err.to_string())?.into_shoe()
+    }))
+}
+
+pub fn save_csv<W: Write>(shoes: &[Shoe], writer: W) -> Result<(), CatalogueError> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for shoe in shoes {
+        writer.serialize(shoe)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn save_json<W: Write>(shoes: &[Shoe], writer: W) -> Result<(), CatalogueError> {
+    serde_json::to_writer_pretty(writer, shoes)?;
+    Ok(())
+}
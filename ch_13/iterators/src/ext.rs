@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::iter::{Fuse, FusedIterator};
+
+// Extra adapters for any iterator. Like the ones in the standard library they
+// are lazy: nothing is pulled from the underlying iterator until you ask for
+// the next item.
+pub trait IteratorExt: Iterator + Sized {
+    // every run of `size` consecutive items, sliding by one; panics if `size` is 0
+    fn windows(self, size: usize) -> Windows<Self> where Self::Item: Clone {
+        assert!(size != 0, "window size must not be zero");
+        Windows {
+            iter: self.fuse(),
+            size,
+            window: VecDeque::with_capacity(size),
+        }
+    }
+
+    // the items `size` at a time; the last chunk may be shorter. Panics if `size` is 0
+    fn chunks(self, size: usize) -> Chunks<Self> {
+        assert!(size != 0, "chunk size must not be zero");
+        Chunks { iter: self.fuse(), size }
+    }
+
+    // runs of consecutive items with the same key, as `(key, items)`
+    fn group_by<K, F>(self, key: F) -> GroupBy<Self, F> where F: FnMut(&Self::Item) -> K, K: PartialEq {
+        GroupBy {
+            iter: self.fuse(),
+            key,
+            pending: None,
+        }
+    }
+
+    // one item from each in turn; once either runs out the other carries on alone
+    fn interleave<J>(self, other: J) -> Interleave<Self, J::IntoIter> where J: IntoIterator<Item = Self::Item> {
+        Interleave {
+            a: self.fuse(),
+            b: other.into_iter().fuse(),
+            take_b: false,
+        }
+    }
+
+    // drops items whose key equals the key of the item just before them
+    fn dedup_by_key<K, F>(self, key: F) -> DedupByKey<Self, F, K> where F: FnMut(&Self::Item) -> K, K: PartialEq {
+        DedupByKey {
+            iter: self.fuse(),
+            key,
+            last: None,
+        }
+    }
+
+    // calls `f` with the iterator for every item; `f` takes as many items as it
+    // likes and returns `None` to stop
+    fn batching<B, F>(self, f: F) -> Batching<Self, F> where F: FnMut(&mut Self) -> Option<B> {
+        Batching { iter: self, f }
+    }
+}
+
+impl<I: Iterator> IteratorExt for I {}
+
+#[derive(Clone, Debug)]
+pub struct Windows<I: Iterator> {
+    iter: Fuse<I>,
+    size: usize,
+    // the last window handed out, or the items gathered for the first one
+    window: VecDeque<I::Item>,
+}
+
+impl<I> Iterator for Windows<I> where I: Iterator, I::Item: Clone {
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        while self.window.len() < self.size {
+            self.window.push_back(self.iter.next()?);
+        }
+        Some(self.window.iter().cloned().collect())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        if self.window.len() == self.size {
+            // every further item moves the window along once
+            (lo, hi)
+        } else {
+            let missing = self.size - self.window.len() - 1;
+            (lo.saturating_sub(missing), hi.map(|hi| hi.saturating_sub(missing)))
+        }
+    }
+}
+
+impl<I> FusedIterator for Windows<I> where I: Iterator, I::Item: Clone {}
+
+#[derive(Clone, Debug)]
+pub struct Chunks<I> {
+    iter: Fuse<I>,
+    size: usize,
+}
+
+impl<I: Iterator> Iterator for Chunks<I> {
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk: Vec<I::Item> = self.iter.by_ref().take(self.size).collect();
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        (lo.div_ceil(self.size), hi.map(|hi| hi.div_ceil(self.size)))
+    }
+}
+
+impl<I: Iterator> FusedIterator for Chunks<I> {}
+
+#[derive(Clone, Debug)]
+pub struct GroupBy<I: Iterator, F> {
+    iter: Fuse<I>,
+    key: F,
+    // the first item of the next group, already taken from `iter`
+    pending: Option<I::Item>,
+}
+
+impl<I, F, K> Iterator for GroupBy<I, F> where I: Iterator, F: FnMut(&I::Item) -> K, K: PartialEq {
+    type Item = (K, Vec<I::Item>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.pending.take().or_else(|| self.iter.next())?;
+        let key = (self.key)(&first);
+        let mut group = vec![first];
+
+        for item in self.iter.by_ref() {
+            if (self.key)(&item) == key {
+                group.push(item);
+            } else {
+                self.pending = Some(item);
+                break;
+            }
+        }
+
+        Some((key, group))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        let pending = self.pending.is_some() as usize;
+        // anything left makes at least one group, and at most one group per item
+        ((lo + pending).min(1), hi.and_then(|hi| hi.checked_add(pending)))
+    }
+}
+
+impl<I, F, K> FusedIterator for GroupBy<I, F> where I: Iterator, F: FnMut(&I::Item) -> K, K: PartialEq {}
+
+#[derive(Clone, Debug)]
+pub struct Interleave<I, J> {
+    a: Fuse<I>,
+    b: Fuse<J>,
+    take_b: bool,
+}
+
+impl<I, J> Iterator for Interleave<I, J> where I: Iterator, J: Iterator<Item = I::Item> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.take_b = !self.take_b;
+        if self.take_b {
+            self.a.next().or_else(|| self.b.next())
+        } else {
+            self.b.next().or_else(|| self.a.next())
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_lo, a_hi) = self.a.size_hint();
+        let (b_lo, b_hi) = self.b.size_hint();
+        let hi = match (a_hi, b_hi) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        (a_lo.saturating_add(b_lo), hi)
+    }
+}
+
+impl<I, J> FusedIterator for Interleave<I, J> where I: Iterator, J: Iterator<Item = I::Item> {}
+
+#[derive(Clone, Debug)]
+pub struct DedupByKey<I, F, K> {
+    iter: Fuse<I>,
+    key: F,
+    last: Option<K>,
+}
+
+impl<I, F, K> Iterator for DedupByKey<I, F, K> where I: Iterator, F: FnMut(&I::Item) -> K, K: PartialEq {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.iter.by_ref() {
+            let key = (self.key)(&item);
+            if self.last.as_ref() != Some(&key) {
+                self.last = Some(key);
+                return Some(item);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        // before the first item nothing can be a duplicate
+        let lo = if self.last.is_none() { lo.min(1) } else { 0 };
+        (lo, hi)
+    }
+}
+
+impl<I, F, K> FusedIterator for DedupByKey<I, F, K> where I: Iterator, F: FnMut(&I::Item) -> K, K: PartialEq {}
+
+#[derive(Clone, Debug)]
+pub struct Batching<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<B, I, F> Iterator for Batching<I, F> where I: Iterator, F: FnMut(&mut I) -> Option<B> {
+    type Item = B;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.f)(&mut self.iter)
+    }
+
+    // `f` may make any number of batches from any number of items
+}
@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hash;
+
+use crate::Shoe;
+
+// Handed out by `ShoeInventory::insert`. Ids are never reused, so an old id
+// can't remove a shoe that was added later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShoeId(u64);
+
+// Shoes indexed by size and by style (ignoring case). Iteration and query
+// results come back in insertion order.
+#[derive(Debug, Default)]
+pub struct ShoeInventory {
+    shoes: BTreeMap<ShoeId, Shoe>,
+    by_size: HashMap<u32, BTreeSet<ShoeId>>,
+    by_style: HashMap<String, BTreeSet<ShoeId>>,
+    next_id: u64,
+}
+
+impl ShoeInventory {
+    pub fn new() -> ShoeInventory {
+        ShoeInventory::default()
+    }
+
+    pub fn insert(&mut self, shoe: Shoe) -> ShoeId {
+        let id = ShoeId(self.next_id);
+        self.next_id += 1;
+
+        self.by_size.entry(shoe.size).or_default().insert(id);
+        self.by_style.entry(shoe.style.to_lowercase()).or_default().insert(id);
+        self.shoes.insert(id, shoe);
+
+        id
+    }
+
+    pub fn remove(&mut self, id: ShoeId) -> Option<Shoe> {
+        let shoe = self.shoes.remove(&id)?;
+
+        remove_from_index(&mut self.by_size, &shoe.size, id);
+        remove_from_index(&mut self.by_style, &shoe.style.to_lowercase(), id);
+
+        Some(shoe)
+    }
+
+    pub fn get(&self, id: ShoeId) -> Option<&Shoe> {
+        self.shoes.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shoes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shoes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ShoeId, &Shoe)> {
+        self.shoes.iter().map(|(id, shoe)| (*id, shoe))
+    }
+
+    pub fn query(&self) -> Query<'_> {
+        Query {
+            inventory: self,
+            size: None,
+            style: None,
+            style_contains: None,
+        }
+    }
+}
+
+fn remove_from_index<K>(index: &mut HashMap<K, BTreeSet<ShoeId>>, key: &K, id: ShoeId)
+    where K: Hash + Eq {
+    if let Some(ids) = index.get_mut(key) {
+        ids.remove(&id);
+        if ids.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+impl FromIterator<Shoe> for ShoeInventory {
+    fn from_iter<I: IntoIterator<Item = Shoe>>(shoes: I) -> ShoeInventory {
+        let mut inventory = ShoeInventory::new();
+        for shoe in shoes {
+            inventory.insert(shoe);
+        }
+        inventory
+    }
+}
+
+// Built by `ShoeInventory::query`; every condition added must hold. Iterating
+// borrows the inventory, it never takes shoes out of it.
+#[derive(Clone, Debug)]
+pub struct Query<'a> {
+    inventory: &'a ShoeInventory,
+    size: Option<u32>,
+    style: Option<String>,
+    style_contains: Option<String>,
+}
+
+impl<'a> Query<'a> {
+    pub fn size(mut self, size: u32) -> Query<'a> {
+        self.size = Some(size);
+        self
+    }
+
+    // the whole style, ignoring case
+    pub fn style(mut self, style: &str) -> Query<'a> {
+        self.style = Some(style.to_lowercase());
+        self
+    }
+
+    // part of the style, ignoring case
+    pub fn style_contains(mut self, text: &str) -> Query<'a> {
+        self.style_contains = Some(text.to_lowercase());
+        self
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &'a Shoe> + 'a> {
+        let inventory = self.inventory;
+        let size = self.size;
+        let style = self.style.clone();
+        let style_contains = self.style_contains.clone();
+
+        // start from the smallest index we can use and filter the rest
+        let candidates: Box<dyn Iterator<Item = &'a Shoe> + 'a> = match (size, &style) {
+            (Some(size), _) => Box::new(lookup(&inventory.by_size, &size, inventory)),
+            (None, Some(style)) => Box::new(lookup(&inventory.by_style, style, inventory)),
+            (None, None) => Box::new(inventory.shoes.values()),
+        };
+
+        Box::new(candidates.filter(move |shoe| {
+            size.is_none_or(|size| shoe.size == size)
+                && style.as_ref().is_none_or(|style| shoe.style.to_lowercase() == *style)
+                && style_contains.as_ref().is_none_or(|text| shoe.style.to_lowercase().contains(text.as_str()))
+        }))
+    }
+
+    pub fn count(&self) -> usize {
+        self.iter().count()
+    }
+}
+
+fn lookup<'a, K>(index: &'a HashMap<K, BTreeSet<ShoeId>>, key: &K, inventory: &'a ShoeInventory)
+    -> impl Iterator<Item = &'a Shoe> + 'a where K: Hash + Eq {
+    index.get(key).into_iter().flatten().map(move |id| &inventory.shoes[id])
+}
+
+impl<'a> IntoIterator for Query<'a> {
+    type Item = &'a Shoe;
+    type IntoIter = Box<dyn Iterator<Item = &'a Shoe> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &Query<'a> {
+    type Item = &'a Shoe;
+    type IntoIter = Box<dyn Iterator<Item = &'a Shoe> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -1,4 +1,18 @@
-#[derive(PartialEq, Debug)]
+use std::iter::FusedIterator;
+
+use serde::{Deserialize, Serialize};
+
+mod catalogue;
+mod ext;
+mod inventory;
+mod parallel;
+
+pub use catalogue::{load_csv, load_json, save_csv, save_json, CatalogueError, RowError};
+pub use ext::{Batching, Chunks, DedupByKey, GroupBy, Interleave, IteratorExt, Windows};
+pub use inventory::{Query, ShoeId, ShoeInventory};
+pub use parallel::{par_filter_map, par_filter_map_with_threads, par_shoes_in_size};
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct Shoe{
     pub size: u32,
     pub style: String,
@@ -8,27 +22,102 @@ pub fn shoes_in_size(shoes: Vec<Shoe>, shoe_size: u32) -> Vec<Shoe> {
     shoes.into_iter().filter(|s| s.size == shoe_size).collect()
 }
 
+// Counts from `start` towards `end` (exclusive) in steps of `step`, counting
+// down when `end` is below `start`, just like a `Range` with `step_by` and `rev`.
+#[derive(Clone, Debug)]
 pub struct Counter {
-    count: u32,
+    start: u32,
+    step: u32,
+    descending: bool,
+    // indexes of the next values to hand out from each end
+    front: usize,
+    back: usize,
 }
 
 impl Counter {
+    // the classic counter: 1, 2, 3, 4, 5
     pub fn new() -> Counter {
+        Counter::range(1, 6)
+    }
+
+    pub fn range(start: u32, end: u32) -> Counter {
+        Counter::stepped(start, end, 1)
+    }
+
+    pub fn stepped(start: u32, end: u32, step: u32) -> Counter {
+        assert!(step != 0, "Counter step must not be zero");
+
+        let distance = start.abs_diff(end) as u64;
+        let len = distance.div_ceil(step as u64) as usize;
+
         Counter {
-            count: 0,
+            start,
+            step,
+            descending: end < start,
+            front: 0,
+            back: len,
+        }
+    }
+
+    fn value(&self, index: usize) -> u32 {
+        let offset = index as u32 * self.step;
+        if self.descending {
+            self.start - offset
+        } else {
+            self.start + offset
         }
     }
 }
 
+impl Default for Counter {
+    fn default() -> Counter {
+        Counter::new()
+    }
+}
+
 impl Iterator for Counter {
     type Item = u32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.count < 5 {
-            self.count += 1;
-            Some(self.count)
+        if self.front < self.back {
+            self.front += 1;
+            Some(self.value(self.front - 1))
         } else {
             None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.front = self.front.saturating_add(n).min(self.back);
+        self.next()
+    }
+
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+}
+
+impl DoubleEndedIterator for Counter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.back -= 1;
+            Some(self.value(self.back))
+        } else {
+            None
+        }
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        self.back = self.back.saturating_sub(n).max(self.front);
+        self.next_back()
+    }
 }
+
+impl ExactSizeIterator for Counter {}
+
+impl FusedIterator for Counter {}
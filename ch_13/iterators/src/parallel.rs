@@ -0,0 +1,59 @@
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::Shoe;
+
+// below this many items per thread, starting threads costs more than it saves
+const MIN_ITEMS_PER_THREAD: usize = 16 * 1024;
+
+// `shoes_in_size`, spread over all cores. The shoes come back in the same
+// order as they went in.
+pub fn par_shoes_in_size(shoes: Vec<Shoe>, shoe_size: u32) -> Vec<Shoe> {
+    par_filter_map(shoes, |s| if s.size == shoe_size { Some(s) } else { None })
+}
+
+// Like `items.into_iter().filter_map(f).collect()`, but the items are split
+// into one contiguous piece per core and each piece runs on its own thread.
+// Small inputs are handled on the calling thread.
+pub fn par_filter_map<T, U, F>(items: Vec<T>, f: F) -> Vec<U>
+    where T: Send, U: Send, F: Fn(T) -> Option<U> + Sync {
+    let cores = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let threads = cores.min(items.len() / MIN_ITEMS_PER_THREAD).max(1);
+    par_filter_map_with_threads(items, threads, f)
+}
+
+// `par_filter_map` with an exact number of threads; 0 is treated as 1
+pub fn par_filter_map_with_threads<T, U, F>(mut items: Vec<T>, threads: usize, f: F) -> Vec<U>
+    where T: Send, U: Send, F: Fn(T) -> Option<U> + Sync {
+    if threads <= 1 {
+        return items.into_iter().filter_map(f).collect();
+    }
+
+    // cut from the back so every split_off only moves its own piece
+    let piece = items.len().div_ceil(threads).max(1);
+    let mut pieces = vec![];
+    while items.len() > piece {
+        let at = (items.len() - 1) / piece * piece;
+        pieces.push(items.split_off(at));
+    }
+    pieces.push(items);
+    pieces.reverse();
+
+    let f = &f;
+    thread::scope(|s| {
+        let handles: Vec<_> = pieces
+            .into_iter()
+            .map(|piece| s.spawn(move || piece.into_iter().filter_map(f).collect::<Vec<U>>()))
+            .collect();
+
+        // joining in spawn order keeps the output in input order
+        let mut results = vec![];
+        for handle in handles {
+            match handle.join() {
+                Ok(part) => results.extend(part),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+        results
+    })
+}
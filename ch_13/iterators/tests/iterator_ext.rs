@@ -0,0 +1,144 @@
+use iterators::{Counter, IteratorExt, Shoe};
+
+// drains `iter`, checking that every `size_hint` along the way brackets the
+// number of items that were really left
+fn assert_size_hints<I: Iterator>(mut iter: I) {
+    let mut hints = vec![iter.size_hint()];
+    while iter.next().is_some() {
+        hints.push(iter.size_hint());
+    }
+
+    let total = hints.len() - 1;
+    for (taken, (lo, hi)) in hints.into_iter().enumerate() {
+        let left = total - taken;
+        assert!(lo <= left, "lower bound {} with {} left", lo, left);
+        assert!(hi.is_none_or(|hi| hi >= left), "upper bound {:?} with {} left", hi, left);
+    }
+}
+
+#[test]
+fn windows_slide_by_one() {
+    let windows: Vec<Vec<u32>> = Counter::new().windows(3).collect();
+    assert_eq!(windows, vec![vec![1, 2, 3], vec![2, 3, 4], vec![3, 4, 5]]);
+
+    assert_eq!(Counter::new().windows(5).count(), 1);
+    assert_eq!(Counter::new().windows(6).next(), None);
+}
+
+#[test]
+fn windows_size_hint() {
+    assert_eq!(Counter::new().windows(2).size_hint(), (4, Some(4)));
+    assert_eq!(Counter::new().windows(9).size_hint(), (0, Some(0)));
+    for size in 1..8 {
+        assert_size_hints(Counter::range(0, 6).windows(size));
+        assert_size_hints(Counter::range(0, 6).filter(|x| x % 2 == 0).windows(size));
+    }
+}
+
+#[test]
+#[should_panic(expected = "window size must not be zero")]
+fn windows_of_zero_panic() {
+    Counter::new().windows(0);
+}
+
+#[test]
+fn chunks_keep_the_remainder() {
+    let chunks: Vec<Vec<u32>> = Counter::new().chunks(2).collect();
+    assert_eq!(chunks, vec![vec![1, 2], vec![3, 4], vec![5]]);
+}
+
+#[test]
+fn chunks_size_hint() {
+    assert_eq!(Counter::new().chunks(2).size_hint(), (3, Some(3)));
+    for size in 1..8 {
+        assert_size_hints(Counter::range(0, 6).chunks(size));
+        assert_size_hints(Counter::range(0, 6).filter(|x| x % 3 != 0).chunks(size));
+    }
+}
+
+#[test]
+fn group_by_groups_neighbours() {
+    let shoes = vec![
+        Shoe { size: 10, style: String::from("Sneaker") },
+        Shoe { size: 10, style: String::from("Boot") },
+        Shoe { size: 13, style: String::from("Sandal") },
+        Shoe { size: 10, style: String::from("Loafer") },
+    ];
+
+    let groups: Vec<(u32, Vec<String>)> = shoes
+        .into_iter()
+        .group_by(|s| s.size)
+        .map(|(size, group)| (size, group.into_iter().map(|s| s.style).collect()))
+        .collect();
+
+    assert_eq!(groups, vec![
+        (10, vec![String::from("Sneaker"), String::from("Boot")]),
+        (13, vec![String::from("Sandal")]),
+        (10, vec![String::from("Loafer")]),
+    ]);
+}
+
+#[test]
+fn group_by_size_hint() {
+    assert_eq!(Counter::new().group_by(|x| x / 2).size_hint(), (1, Some(5)));
+    assert_eq!(Counter::range(0, 0).group_by(|x| *x).size_hint(), (0, Some(0)));
+    assert_size_hints(Counter::range(0, 10).group_by(|x| x / 3));
+    assert_size_hints(Counter::range(0, 10).group_by(|x| *x));
+}
+
+#[test]
+fn interleave_alternates_then_drains() {
+    let mixed: Vec<u32> = Counter::range(1, 4).interleave(Counter::range(10, 16)).collect();
+    assert_eq!(mixed, vec![1, 10, 2, 11, 3, 12, 13, 14, 15]);
+
+    let mixed: Vec<u32> = Counter::range(1, 6).interleave(vec![10]).collect();
+    assert_eq!(mixed, vec![1, 10, 2, 3, 4, 5]);
+}
+
+#[test]
+fn interleave_size_hint() {
+    assert_eq!(Counter::new().interleave(Counter::new()).size_hint(), (10, Some(10)));
+    assert_size_hints(Counter::range(0, 3).interleave(Counter::range(0, 7)));
+    assert_size_hints(Counter::range(0, 7).interleave(Counter::range(0, 7).filter(|x| x % 2 == 0)));
+}
+
+#[test]
+fn dedup_by_key_drops_repeats() {
+    let words = vec!["apple", "avocado", "banana", "blueberry", "apricot"];
+    let firsts: Vec<&str> = words.into_iter().dedup_by_key(|w| w.chars().next()).collect();
+    assert_eq!(firsts, vec!["apple", "banana", "apricot"]);
+}
+
+#[test]
+fn dedup_by_key_size_hint() {
+    assert_eq!(Counter::new().dedup_by_key(|x| x / 10).size_hint(), (1, Some(5)));
+    assert_size_hints(Counter::range(0, 10).dedup_by_key(|x| x / 4));
+    assert_size_hints(Counter::range(0, 10).dedup_by_key(|x| *x));
+    assert_size_hints(Counter::range(0, 10).dedup_by_key(|_| 0));
+}
+
+#[test]
+fn batching_sums_pairs() {
+    let sums: Vec<u32> = Counter::new()
+        .batching(|it| it.next().map(|a| a + it.next().unwrap_or(0)))
+        .collect();
+    assert_eq!(sums, vec![3, 7, 5]);
+}
+
+#[test]
+fn batching_size_hint() {
+    assert_size_hints(Counter::new().batching(|it| it.next()));
+}
+
+#[test]
+fn adapters_are_lazy() {
+    use std::cell::Cell;
+
+    let pulled = Cell::new(0);
+    let counted = Counter::range(0, 100).inspect(|_| pulled.set(pulled.get() + 1));
+
+    let mut windows = counted.windows(3).chunks(2);
+    assert_eq!(pulled.get(), 0);
+    assert_eq!(windows.next(), Some(vec![vec![0, 1, 2], vec![1, 2, 3]]));
+    assert_eq!(pulled.get(), 4);
+}
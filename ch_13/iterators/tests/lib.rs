@@ -74,3 +74,262 @@ fn using_other_iterator_trait_methods() {
         .sum();
     assert_eq!(18, sum);
 }
+
+#[test]
+fn counter_with_range_and_step() {
+    use iterators::Counter;
+
+    assert_eq!(Counter::range(3, 7).collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+    assert_eq!(Counter::stepped(0, 10, 3).collect::<Vec<_>>(), vec![0, 3, 6, 9]);
+    assert_eq!(Counter::stepped(0, 9, 3).collect::<Vec<_>>(), vec![0, 3, 6]);
+    assert_eq!(Counter::range(5, 5).next(), None);
+}
+
+#[test]
+fn counter_counts_down() {
+    use iterators::Counter;
+
+    assert_eq!(Counter::range(5, 0).collect::<Vec<_>>(), vec![5, 4, 3, 2, 1]);
+    assert_eq!(Counter::stepped(10, 0, 4).collect::<Vec<_>>(), vec![10, 6, 2]);
+}
+
+#[test]
+fn counter_matches_ranges() {
+    use iterators::Counter;
+
+    for (start, end, step) in [(0, 20, 1), (1, 20, 3), (7, 8, 5), (4, 4, 2), (0, u32::MAX, u32::MAX / 3)] {
+        let expected: Vec<u32> = (start..end).step_by(step as usize).collect();
+        assert_eq!(Counter::stepped(start, end, step).collect::<Vec<_>>(), expected);
+        assert_eq!(Counter::stepped(start, end, step).rev().collect::<Vec<_>>(),
+                   expected.iter().rev().copied().collect::<Vec<_>>());
+        assert_eq!(Counter::stepped(start, end, step).len(), expected.len());
+    }
+}
+
+#[test]
+fn counter_reports_exact_size() {
+    use iterators::Counter;
+
+    let mut c = Counter::stepped(0, 10, 2);
+    assert_eq!(c.size_hint(), (5, Some(5)));
+    c.next();
+    c.next_back();
+    assert_eq!(c.len(), 3);
+    assert_eq!(Counter::new().len(), 5);
+}
+
+#[test]
+fn counter_from_both_ends() {
+    use iterators::Counter;
+
+    let mut c = Counter::range(1, 5);
+    assert_eq!(c.next(), Some(1));
+    assert_eq!(c.next_back(), Some(4));
+    assert_eq!(c.next(), Some(2));
+    assert_eq!(c.next_back(), Some(3));
+    assert_eq!(c.next(), None);
+    assert_eq!(c.next_back(), None);
+}
+
+#[test]
+fn counter_nth_skips_ahead() {
+    use iterators::Counter;
+
+    let mut c = Counter::stepped(0, 100, 10);
+    assert_eq!(c.nth(2), Some(20));
+    assert_eq!(c.nth_back(1), Some(80));
+    assert_eq!(c.len(), 5);
+    assert_eq!(c.nth(100), None);
+    assert_eq!(c.next(), None);
+    assert_eq!(c.next_back(), None);
+}
+
+#[test]
+#[should_panic(expected = "step must not be zero")]
+fn counter_rejects_zero_step() {
+    iterators::Counter::stepped(0, 10, 0);
+}
+
+fn shoe(size: u32, style: &str) -> iterators::Shoe {
+    iterators::Shoe {
+        size,
+        style: style.to_string(),
+    }
+}
+
+#[test]
+fn inventory_queries_by_size_and_style() {
+    use iterators::ShoeInventory;
+
+    let inventory: ShoeInventory = vec![
+        shoe(10, "Sneaker"),
+        shoe(13, "Sandal"),
+        shoe(10, "Boot"),
+        shoe(10, "Hiking Boot"),
+        shoe(12, "Boot"),
+    ].into_iter().collect();
+
+    let boots_in_my_size: Vec<_> = inventory.query().size(10).style_contains("boot").into_iter().collect();
+    assert_eq!(boots_in_my_size, vec![&shoe(10, "Boot"), &shoe(10, "Hiking Boot")]);
+
+    let boots: Vec<_> = inventory.query().style("BOOT").into_iter().collect();
+    assert_eq!(boots, vec![&shoe(10, "Boot"), &shoe(12, "Boot")]);
+
+    assert_eq!(inventory.query().size(10).count(), 3);
+    assert_eq!(inventory.query().count(), 5);
+    assert_eq!(inventory.query().size(11).count(), 0);
+    assert_eq!(inventory.query().style("boot").style_contains("hik").count(), 0);
+
+    // querying only borrows, so the inventory is still all there
+    assert_eq!(inventory.len(), 5);
+}
+
+#[test]
+fn inventory_query_can_be_reused() {
+    use iterators::ShoeInventory;
+
+    let inventory: ShoeInventory = vec![shoe(9, "Loafer"), shoe(9, "Boot")].into_iter().collect();
+    let nines = inventory.query().size(9);
+
+    let mut styles = vec![];
+    for s in &nines {
+        styles.push(s.style.as_str());
+    }
+    assert_eq!(styles, vec!["Loafer", "Boot"]);
+    assert_eq!(nines.count(), 2);
+}
+
+#[test]
+fn inventory_indexes_follow_inserts_and_removals() {
+    use iterators::ShoeInventory;
+
+    let mut inventory = ShoeInventory::new();
+    let sneaker = inventory.insert(shoe(10, "Sneaker"));
+    let boot = inventory.insert(shoe(10, "Boot"));
+    assert_eq!(inventory.query().size(10).count(), 2);
+
+    assert_eq!(inventory.remove(boot), Some(shoe(10, "Boot")));
+    assert_eq!(inventory.remove(boot), None);
+    assert_eq!(inventory.query().size(10).count(), 1);
+    assert_eq!(inventory.query().style("boot").count(), 0);
+
+    let new_boot = inventory.insert(shoe(11, "Boot"));
+    assert_ne!(new_boot, boot);
+    assert_eq!(inventory.get(boot), None);
+    assert_eq!(inventory.get(new_boot), Some(&shoe(11, "Boot")));
+    assert_eq!(inventory.query().style("boot").size(11).count(), 1);
+    assert_eq!(inventory.query().style("boot").size(10).count(), 0);
+
+    inventory.remove(sneaker);
+    inventory.remove(new_boot);
+    assert!(inventory.is_empty());
+    assert_eq!(inventory.query().count(), 0);
+    assert_eq!(inventory.iter().count(), 0);
+}
+
+#[test]
+fn loads_csv_catalogue_into_shoes_in_size() {
+    use iterators::{load_csv, shoes_in_size};
+
+    let csv = "style,size\nSneaker,10\nSandal,13\n Boot , 10\n";
+    let shoes = load_csv(csv.as_bytes()).unwrap();
+
+    assert_eq!(shoes_in_size(shoes, 10), vec![shoe(10, "Sneaker"), shoe(10, "Boot")]);
+}
+
+#[test]
+fn reports_every_bad_csv_row() {
+    use iterators::{load_csv, CatalogueError, RowError};
+
+    let csv = "size,style\n10,Sneaker\nten,Boot\n9,\n-1,Sandal\n,Loafer\n";
+    match load_csv(csv.as_bytes()) {
+        Err(CatalogueError::Rows(errors)) => assert_eq!(errors, vec![
+            RowError { row: 2, message: "invalid size `ten`".to_string() },
+            RowError { row: 3, message: "missing style".to_string() },
+            RowError { row: 4, message: "invalid size `-1`".to_string() },
+            RowError { row: 5, message: "missing size".to_string() },
+        ]),
+        other => panic!("expected row errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn csv_needs_size_and_style_columns() {
+    use iterators::{load_csv, CatalogueError};
+
+    let err = load_csv("size,colour\n10,red\n".as_bytes()).unwrap_err();
+    assert!(matches!(err, CatalogueError::MissingColumn("style")));
+    assert_eq!(err.to_string(), "header has no `style` column");
+}
+
+#[test]
+fn loads_json_catalogue() {
+    use iterators::load_json;
+
+    let json = r#"[{"size": 10, "style": "Sneaker"}, {"size": "13", "style": "Sandal"}]"#;
+    assert_eq!(load_json(json.as_bytes()).unwrap(), vec![shoe(10, "Sneaker"), shoe(13, "Sandal")]);
+}
+
+#[test]
+fn reports_every_bad_json_row() {
+    use iterators::{load_json, CatalogueError};
+
+    let json = r#"[{"size": 10.5, "style": "Boot"}, {"size": 9}, {"size": 8, "style": "Clog"}, 7]"#;
+    let err = load_json(json.as_bytes()).unwrap_err();
+    assert!(matches!(&err, CatalogueError::Rows(errors) if errors.len() == 3));
+    assert_eq!(err.to_string(), "row 1: invalid size `10.5`\nrow 2: missing style\nrow 4: expected an object");
+}
+
+#[test]
+fn catalogues_round_trip() {
+    use iterators::{load_csv, load_json, save_csv, save_json};
+
+    let shoes = vec![shoe(10, "Sneaker"), shoe(13, "Sandal, open toe")];
+
+    let mut csv = vec![];
+    save_csv(&shoes, &mut csv).unwrap();
+    assert_eq!(load_csv(csv.as_slice()).unwrap(), shoes);
+
+    let mut json = vec![];
+    save_json(&shoes, &mut json).unwrap();
+    assert_eq!(load_json(json.as_slice()).unwrap(), shoes);
+}
+
+#[test]
+fn parallel_filter_matches_sequential() {
+    use iterators::{par_shoes_in_size, shoes_in_size};
+
+    let catalogue = || -> Vec<iterators::Shoe> {
+        (0..100_000).map(|i| shoe(5 + i % 7, &format!("style {}", i))).collect()
+    };
+
+    let sequential = shoes_in_size(catalogue(), 9);
+    let parallel = par_shoes_in_size(catalogue(), 9);
+    assert_eq!(parallel.len(), 14_286);
+    assert_eq!(parallel, sequential);
+}
+
+#[test]
+fn parallel_filter_map_keeps_input_order() {
+    use iterators::par_filter_map_with_threads;
+
+    for threads in [0, 1, 2, 3, 8, 64] {
+        for len in [0, 1, 5, 63, 64, 65, 1000] {
+            let items: Vec<u32> = (0..len).collect();
+            let expected: Vec<u32> = items.iter().filter(|x| *x % 3 == 0).map(|x| x * 2).collect();
+            let got = par_filter_map_with_threads(items, threads, |x| if x % 3 == 0 { Some(x * 2) } else { None });
+            assert_eq!(got, expected, "{} threads, {} items", threads, len);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "bad shoe")]
+fn parallel_filter_map_passes_on_panics() {
+    iterators::par_filter_map_with_threads((0..10).collect(), 4, |x: u32| -> Option<u32> {
+        if x == 7 {
+            panic!("bad shoe");
+        }
+        Some(x)
+    });
+}
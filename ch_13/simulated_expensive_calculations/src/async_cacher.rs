@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+use futures::future::{FutureExt, Shared};
+
+// The async counterpart of `Cacher`: `calculation` returns a future instead of
+// a value. Every caller asking for a key that is still being computed awaits
+// the same shared future, so the calculation runs once per key. `new` is for
+// calculations that can fail, and keeps only what succeeded;
+// `new_caching_all` keeps every output.
+pub struct AsyncCacher<F, K, Fut> where F: Fn(K) -> Fut, Fut: Future {
+    calculation: F,
+    retain: fn(&Fut::Output) -> bool,
+    values: Mutex<HashMap<K, Shared<Fut>>>,
+}
+
+impl<F, K, Fut> AsyncCacher<F, K, Fut>
+    where F: Fn(K) -> Fut, K: Eq + Hash + Clone, Fut: Future, Fut::Output: Clone {
+    // Every output is kept, an `Err` as much as anything else, so a failed
+    // calculation is never retried; `new` retries them.
+    pub fn new_caching_all(calculation: F) -> AsyncCacher<F, K, Fut> {
+        AsyncCacher {
+            calculation,
+            retain: |_| true,
+            values: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn value(&self, arg: K) -> Fut::Output {
+        let cached = self.values.lock().unwrap().get(&arg).cloned();
+        let shared = match cached {
+            Some(shared) => shared,
+            None => {
+                // Made without the lock held, so a slow calculation holds up
+                // only its own key. If another caller got in first, theirs is
+                // kept and this one never runs.
+                let fresh = (self.calculation)(arg.clone()).shared();
+                self.values.lock().unwrap().entry(arg.clone()).or_insert(fresh).clone()
+            }
+        };
+
+        let _forget = ForgetOnPanic {
+            values: &self.values,
+            key: &arg,
+            shared: &shared,
+        };
+        let v = shared.clone().await;
+
+        if !(self.retain)(&v) {
+            // only forget our own future; someone may already have started a retry
+            let mut values = self.values.lock().unwrap();
+            if values.get(&arg).is_some_and(|s| s.ptr_eq(&shared)) {
+                values.remove(&arg);
+            }
+        }
+
+        v
+    }
+}
+
+// A `Shared` whose future panicked panics again every time it's awaited, so
+// if awaiting it unwinds, the key's entry goes and the next call starts over.
+struct ForgetOnPanic<'a, K: Eq + Hash, Fut: Future> {
+    values: &'a Mutex<HashMap<K, Shared<Fut>>>,
+    key: &'a K,
+    shared: &'a Shared<Fut>,
+}
+
+impl<K: Eq + Hash, Fut: Future> Drop for ForgetOnPanic<'_, K, Fut> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let mut values = self.values.lock().unwrap_or_else(PoisonError::into_inner);
+            if values.get(self.key).is_some_and(|s| s.ptr_eq(self.shared)) {
+                values.remove(self.key);
+            }
+        }
+    }
+}
+
+impl<F, K, Fut, T, E> AsyncCacher<F, K, Fut>
+    where F: Fn(K) -> Fut, K: Eq + Hash + Clone, Fut: Future<Output = Result<T, E>>, T: Clone, E: Clone {
+    // An `Err` is handed to everyone waiting on it and then dropped, so the
+    // next call for that key runs the calculation again.
+    pub fn new(calculation: F) -> AsyncCacher<F, K, Fut> {
+        AsyncCacher {
+            calculation,
+            retain: Result::is_ok,
+            values: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::join;
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    // returns Pending once so that joined callers really overlap
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn caches_values_per_key() {
+        let calls = Cell::new(0);
+        let c = AsyncCacher::new_caching_all(|x: u32| {
+            calls.set(calls.get() + 1);
+            async move { x * 2 }
+        });
+
+        block_on(async {
+            assert_eq!(c.value(1).await, 2);
+            assert_eq!(c.value(1).await, 2);
+            assert_eq!(c.value(2).await, 4);
+        });
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn concurrent_callers_share_one_calculation() {
+        let calls = Cell::new(0);
+        let c = AsyncCacher::new_caching_all(|x: u32| {
+            calls.set(calls.get() + 1);
+            async move {
+                YieldNow(false).await;
+                x + 1
+            }
+        });
+
+        let (a, b, d) = block_on(async { join!(c.value(5), c.value(5), c.value(5)) });
+        assert_eq!((a, b, d), (6, 6, 6));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn new_caching_all_keeps_errors_too() {
+        let calls = Cell::new(0);
+        let c = AsyncCacher::new_caching_all(|_: u32| {
+            calls.set(calls.get() + 1);
+            async move { Err::<u32, _>("timeout") }
+        });
+
+        block_on(async {
+            assert_eq!(c.value(1).await, Err("timeout"));
+            assert_eq!(c.value(1).await, Err("timeout"));
+        });
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn a_panicking_calculation_leaves_other_keys_alone() {
+        let c = AsyncCacher::new_caching_all(|x: u32| {
+            assert!(x != 0, "no value for 0");
+            async move { x * 2 }
+        });
+
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| block_on(c.value(0))));
+        assert!(panicked.is_err());
+        assert_eq!(block_on(c.value(3)), 6);
+    }
+
+    #[test]
+    fn a_calculation_that_panics_is_run_again() {
+        let calls = Cell::new(0);
+        let c = AsyncCacher::new_caching_all(|x: u32| {
+            calls.set(calls.get() + 1);
+            let attempt = calls.get();
+            async move {
+                YieldNow(false).await;
+                assert!(attempt > 1, "lost the connection");
+                x * 2
+            }
+        });
+
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| block_on(c.value(4))));
+        assert!(panicked.is_err());
+        assert_eq!(block_on(c.value(4)), 8);
+        assert_eq!(block_on(c.value(4)), 8);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let calls = Cell::new(0);
+        let c = AsyncCacher::new(|x: u32| {
+            calls.set(calls.get() + 1);
+            let attempt = calls.get();
+            async move {
+                if attempt == 1 {
+                    Err(String::from("connection reset"))
+                } else {
+                    Ok(x)
+                }
+            }
+        });
+
+        block_on(async {
+            assert_eq!(c.value(7).await, Err(String::from("connection reset")));
+            assert_eq!(c.value(7).await, Ok(7));
+            assert_eq!(c.value(7).await, Ok(7));
+        });
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn concurrent_callers_share_an_error() {
+        let calls = Cell::new(0);
+        let c = AsyncCacher::new(|_: u32| {
+            calls.set(calls.get() + 1);
+            async move {
+                YieldNow(false).await;
+                Err::<u32, _>("timeout")
+            }
+        });
+
+        let (a, b) = block_on(async { join!(c.value(1), c.value(1)) });
+        assert_eq!((a, b), (Err("timeout"), Err("timeout")));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+
+use crate::plan::{describe, Workout};
+
+const ICS_LINE_LIMIT: usize = 75;
+
+// One row per exercise, plus a single row for each rest day. `days[0]` falls
+// on `start`, `days[1]` on the day after, and so on.
+pub fn to_csv(days: &[Workout], start: NaiveDate) -> String {
+    let mut csv = String::from("day,date,exercise,amount,unit\n");
+
+    for (i, workout) in days.iter().enumerate() {
+        let date = day_date(start, i);
+        match workout {
+            Workout::Rest => csv.push_str(&format!("{},{},rest,,\n", i + 1, date)),
+            Workout::Exercises(exercises) => {
+                for e in exercises {
+                    csv.push_str(&format!("{},{},{},{},{}\n", i + 1, date, csv_field(&e.name), e.amount,
+                                          csv_field(&e.unit)));
+                }
+            }
+        }
+    }
+
+    csv
+}
+
+// An iCalendar file with one all-day event per training session; rest days
+// get no event. The exercises go in the event description. `created` is
+// when the file is made, the DTSTAMP of every event.
+pub fn to_ics(days: &[Workout], start: NaiveDate, created: DateTime<Utc>) -> String {
+    let stamp = created.format("%Y%m%dT%H%M%SZ");
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//simulated_expensive_calculations//workout plan//EN"),
+    ];
+
+    for (i, workout) in days.iter().enumerate() {
+        let exercises = match workout {
+            Workout::Rest => continue,
+            Workout::Exercises(exercises) => exercises,
+        };
+
+        let date = day_date(start, i).format("%Y%m%d");
+        let description: Vec<String> = exercises.iter().map(describe).collect();
+
+        lines.push(String::from("BEGIN:VEVENT"));
+        lines.push(format!("UID:workout-{}-day{}@simulated_expensive_calculations", date, i + 1));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", date));
+        lines.push(format!("DTEND;VALUE=DATE:{}", day_date(start, i + 1).format("%Y%m%d")));
+        lines.push(format!("SUMMARY:Workout day {}", i + 1));
+        lines.push(format!("DESCRIPTION:{}", ics_text(&description.join("\n"))));
+        lines.push(String::from("END:VEVENT"));
+    }
+
+    lines.push(String::from("END:VCALENDAR"));
+
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold(&line));
+        ics.push_str("\r\n");
+    }
+    ics
+}
+
+fn day_date(start: NaiveDate, day: usize) -> NaiveDate {
+    start + Days::new(day as u64)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+// RFC 5545 section 3.3.11
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// RFC 5545 section 3.1: lines longer than 75 octets continue on the next
+// line after a single space, without splitting a UTF-8 character
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+
+    for c in line.chars() {
+        if width + c.len_utf8() > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::Exercise;
+
+    fn exercise(name: &str, amount: u32, unit: &str) -> Exercise {
+        Exercise { name: String::from(name), amount, unit: String::from(unit) }
+    }
+
+    fn days() -> Vec<Workout> {
+        vec![
+            Workout::Exercises(vec![exercise("pushups", 10, "reps"), exercise("situps", 12, "reps")]),
+            Workout::Rest,
+            Workout::Exercises(vec![exercise("running", 30, "minutes")]),
+        ]
+    }
+
+    fn start() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 2, 28).unwrap()
+    }
+
+    fn created() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_706_000_000, 0).unwrap()
+    }
+
+    #[test]
+    fn csv_has_a_row_per_exercise_and_rest_day() {
+        assert_eq!(to_csv(&days(), start()), "\
+day,date,exercise,amount,unit
+1,2024-02-28,pushups,10,reps
+1,2024-02-28,situps,12,reps
+2,2024-02-29,rest,,
+3,2024-03-01,running,30,minutes
+");
+    }
+
+    #[test]
+    fn csv_quotes_awkward_names() {
+        let days = vec![Workout::Exercises(vec![exercise("curls, \"hammer\"", 8, "reps")])];
+        assert!(to_csv(&days, start()).contains("1,2024-02-28,\"curls, \"\"hammer\"\"\",8,reps\n"));
+    }
+
+    #[test]
+    fn ics_has_an_event_per_session() {
+        let ics = to_ics(&days(), start(), created());
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240228\r\nDTEND;VALUE=DATE:20240229\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240301\r\n"));
+        // both events were made when the file was, whatever their dates
+        assert_eq!(ics.matches("DTSTAMP:20240123T085320Z\r\n").count(), 2);
+        assert!(ics.contains("DESCRIPTION:10 pushups\\n12 situps\r\n"));
+        assert!(ics.contains("DESCRIPTION:30 minutes of running\r\n"));
+    }
+
+    #[test]
+    fn ics_escapes_and_folds_long_lines() {
+        let name = "a, very; long exercise name that will not fit on a single calendar line";
+        let ics = to_ics(&[Workout::Exercises(vec![exercise(name, 1, "reps")])], start(), created());
+
+        assert!(ics.lines().all(|l| l.trim_end_matches('\r').len() <= ICS_LINE_LIMIT));
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains("DESCRIPTION:1 a\\, very\\; long exercise name"));
+    }
+}
@@ -1,6 +1,25 @@
+use std::path::Path;
 use std::thread;
 use std::time::Duration;
 
+mod async_cacher;
+mod export;
+mod persistent;
+mod plan;
+mod rng;
+mod week;
+
+pub use async_cacher::AsyncCacher;
+pub use export::{to_csv, to_ics};
+pub use persistent::PersistentCacher;
+pub use plan::{render, Exercise, Workout, WorkoutPlan};
+pub use rng::{RandomSource, SeededRng};
+pub use week::{render_week, DAYS_PER_WEEK};
+
+// bump this whenever `calculate_slowly` changes so stale persistent caches
+// are thrown away
+const CALCULATION_FINGERPRINT: &str = "calculate_slowly-v1";
+
 struct CacheMap<T> {
     key: Option<T>,
     value: Option<T>,
@@ -23,23 +42,12 @@ impl<T> Cacher<T> where T: Fn(u32) -> u32 {
     }
 
     fn value(&mut self, arg: u32) -> u32 {
-        match self.value.key {
-            Some(v) => {
-                if v == arg {
-                    return v;
-                } else {
-                    let v = (self.calculation)(arg);
-                    self.value = CacheMap {
-                        key: Some(v),
-                        value: Some(v),
-                    };
-                    v
-                }
-            }
-            None => {
+        match self.value {
+            CacheMap { key: Some(k), value: Some(v) } if k == arg => v,
+            _ => {
                 let v = (self.calculation)(arg);
                 self.value = CacheMap {
-                    key: Some(v),
+                    key: Some(arg),
                     value: Some(v),
                 };
                 v
@@ -54,24 +62,29 @@ pub fn simulated_expensive_calculations(intensity: u32) -> u32 {
     intensity
 }
 
-pub fn generate_workout(intensity: u32, random_number: u32) {
+fn calculate_slowly(num: u32) -> u32 {
+    println!("calculating slowly...");
+    thread::sleep(Duration::from_secs(2));
+    num
+}
+
+pub fn generate_workout(plan: &WorkoutPlan, intensity: u32, random_number: u32) -> Workout {
     // let expensive_result = simulated_expensive_calculations(intensity);
-    let mut expensive_result = Cacher::new(|num| {
-        println!("calculating slowly...");
-        thread::sleep(Duration::from_secs(2));
-        num
-    });
-
-    if intensity < 25 {
-        println!("Today, do {} pushups.", expensive_result.value(intensity));
-        println!("Next, do {} situps.", expensive_result.value(intensity));
-    } else {
-        if random_number == 3 {
-            println!("Take a break, remember to stay hydrated!");
-        } else {
-            println!("Remember to run for {} minutes!", expensive_result.value(intensity));
-        }
-    }
+    let mut expensive_result = Cacher::new(calculate_slowly);
+    plan.generate(intensity, random_number, |num| expensive_result.value(num))
+}
+
+pub fn generate_week(plan: &WorkoutPlan, intensity: u32, seed: u64) -> Vec<Workout> {
+    let mut expensive_result = Cacher::new(calculate_slowly);
+    plan.generate_week(intensity, &mut SeededRng::new(seed), |num| expensive_result.value(num))
+}
+
+// same as `generate_workout`, but the expensive result survives between runs
+// in `cache_dir`
+pub fn generate_workout_with_cache_dir(plan: &WorkoutPlan, intensity: u32, random_number: u32,
+                                       cache_dir: &Path) -> Workout {
+    let mut expensive_result = PersistentCacher::new(calculate_slowly, cache_dir, CALCULATION_FINGERPRINT);
+    plan.generate(intensity, random_number, |num| expensive_result.value(num))
 }
 
 #[cfg(test)]
@@ -92,4 +105,13 @@ mod test {
         println!("V1: {}\nV2: {}", v1, v2);
         assert_eq!(2, v2);
     }
+
+    #[test]
+    fn call_with_same_value_uses_cache() {
+        let mut c = Cacher::new(|x| x * 10);
+
+        assert_eq!(10, c.value(1));
+        assert_eq!(10, c.value(1));
+        assert_eq!(20, c.value(2));
+    }
 }
@@ -1,8 +1,61 @@
-use simulated_expensive_calculations::generate_workout;
+use std::env;
+use std::path::Path;
+use std::process;
+
+use chrono::{Local, NaiveDate, Utc};
+use simulated_expensive_calculations::{
+    generate_week, generate_workout, generate_workout_with_cache_dir, render, render_week, to_csv, to_ics,
+    WorkoutPlan,
+};
 
 fn main() {
     let simulated_user_specified_value = 10;
     let simulated_random_number = 7;
 
-    generate_workout(simulated_user_specified_value, simulated_random_number);
+    // WORKOUT_PLAN points at a rules file like workout_plan.toml; without it the bundled rules are used
+    let plan = match env::var("WORKOUT_PLAN") {
+        Ok(path) => WorkoutPlan::from_file(Path::new(&path)).unwrap_or_else(|err| {
+            eprintln!("Problem loading workout plan {}: {}", path, err);
+            process::exit(1);
+        }),
+        Err(_) => WorkoutPlan::default(),
+    };
+
+    // WORKOUT_SEED asks for a whole week instead of a single day; the same seed always gives the same week
+    let days = if let Ok(seed) = env::var("WORKOUT_SEED") {
+        let seed: u64 = seed.parse().unwrap_or_else(|_| {
+            eprintln!("WORKOUT_SEED must be a number, got {}", seed);
+            process::exit(1);
+        });
+        generate_week(&plan, simulated_user_specified_value, seed)
+    } else {
+        // opt in to keeping results between runs by pointing WORKOUT_CACHE_DIR at a directory
+        let workout = match env::var("WORKOUT_CACHE_DIR") {
+            Ok(dir) => generate_workout_with_cache_dir(&plan, simulated_user_specified_value,
+                                                       simulated_random_number, Path::new(&dir)),
+            Err(_) => generate_workout(&plan, simulated_user_specified_value, simulated_random_number),
+        };
+        vec![workout]
+    };
+
+    // WORKOUT_START is the date of the first day in exported plans, today if unset
+    let start = match env::var("WORKOUT_START") {
+        Ok(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap_or_else(|_| {
+            eprintln!("WORKOUT_START must look like 2024-01-31, got {}", date);
+            process::exit(1);
+        }),
+        Err(_) => Local::now().date_naive(),
+    };
+
+    // WORKOUT_EXPORT=ics or csv prints the plan for calendars or spreadsheets instead of as text
+    match env::var("WORKOUT_EXPORT").as_deref() {
+        Ok("ics") => print!("{}", to_ics(&days, start, Utc::now())),
+        Ok("csv") => print!("{}", to_csv(&days, start)),
+        Ok(other) => {
+            eprintln!("WORKOUT_EXPORT must be ics or csv, got {}", other);
+            process::exit(1);
+        }
+        Err(_) if days.len() == 1 => print!("{}", render(&days[0])),
+        Err(_) => print!("{}", render_week(&days)),
+    }
 }
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const CACHE_FILE: &str = "cacher.cache";
+
+// Memoizes `calculation` in a file under `cache_dir` so later runs of the
+// binary skip the expensive work. The first line of the file holds the
+// fingerprint of the calculation; if it doesn't match, the entries were made
+// by a different version of the calculation and are thrown away.
+pub struct PersistentCacher<T> where T: Fn(u32) -> u32 {
+    calculation: T,
+    fingerprint: String,
+    path: PathBuf,
+    values: Option<HashMap<u32, u32>>,
+}
+
+impl<T> PersistentCacher<T> where T: Fn(u32) -> u32 {
+    pub fn new(calculation: T, cache_dir: &Path, fingerprint: &str) -> PersistentCacher<T> {
+        PersistentCacher {
+            calculation,
+            fingerprint: String::from(fingerprint),
+            path: cache_dir.join(CACHE_FILE),
+            values: None,
+        }
+    }
+
+    pub fn value(&mut self, arg: u32) -> u32 {
+        if let Some(&v) = self.entries().get(&arg) {
+            return v;
+        }
+
+        let v = (self.calculation)(arg);
+        self.entries().insert(arg, v);
+        // the cache only saves time, so a failed write just means the next
+        // run computes the value again
+        let _ = self.save();
+        v
+    }
+
+    // entries are loaded on the first lookup rather than in `new`
+    fn entries(&mut self) -> &mut HashMap<u32, u32> {
+        let path = &self.path;
+        let fingerprint = &self.fingerprint;
+        self.values.get_or_insert_with(|| load(path, fingerprint).unwrap_or_default())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let values = match &self.values {
+            Some(values) => values,
+            None => return Ok(()),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut contents = format!("{}\n", self.fingerprint);
+        for (key, value) in values {
+            contents.push_str(&format!("{} {}\n", key, value));
+        }
+
+        // write to a temporary file first so a crash never leaves a half
+        // written cache behind
+        let tmp = self.path.with_extension("tmp");
+        fs::File::create(&tmp)?.write_all(contents.as_bytes())?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+fn load(path: &Path, fingerprint: &str) -> Option<HashMap<u32, u32>> {
+    let contents = fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+
+    if lines.next()? != fingerprint {
+        return None;
+    }
+
+    let mut values = HashMap::new();
+    for line in lines {
+        let (key, value) = line.split_once(' ')?;
+        values.insert(key.parse().ok()?, value.parse().ok()?);
+    }
+
+    Some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::env;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("persistent_cacher_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn reuses_values_across_instances() {
+        let dir = cache_dir("reuse");
+        let calls = Cell::new(0);
+        let calculation = |x| {
+            calls.set(calls.get() + 1);
+            x * 2
+        };
+
+        let mut first = PersistentCacher::new(&calculation, &dir, "v1");
+        assert_eq!(first.value(3), 6);
+        assert_eq!(first.value(3), 6);
+        assert_eq!(calls.get(), 1);
+
+        let mut second = PersistentCacher::new(&calculation, &dir, "v1");
+        assert_eq!(second.value(3), 6);
+        assert_eq!(calls.get(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn discards_entries_with_other_fingerprint() {
+        let dir = cache_dir("fingerprint");
+
+        let mut old = PersistentCacher::new(|x| x * 2, &dir, "v1");
+        assert_eq!(old.value(3), 6);
+
+        let mut new = PersistentCacher::new(|x| x * 3, &dir, "v2");
+        assert_eq!(new.value(3), 9);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ignores_corrupt_cache_file() {
+        let dir = cache_dir("corrupt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(CACHE_FILE), "v1\n3 not-a-number\n").unwrap();
+
+        let mut c = PersistentCacher::new(|x| x + 1, &dir, "v1");
+        assert_eq!(c.value(3), 4);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
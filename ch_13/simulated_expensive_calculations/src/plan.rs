@@ -0,0 +1,275 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+const DEFAULT_PLAN: &str = include_str!("../workout_plan.toml");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exercise {
+    pub name: String,
+    pub amount: u32,
+    pub unit: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Workout {
+    Exercises(Vec<Exercise>),
+    Rest,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkoutPlan {
+    #[serde(rename = "rule")]
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    #[serde(default)]
+    min_intensity: u32,
+    max_intensity: Option<u32>,
+    rest_on: Option<u32>,
+    #[serde(default)]
+    exercises: Vec<ExerciseRule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExerciseRule {
+    name: String,
+    #[serde(default = "default_unit")]
+    unit: String,
+    amount: RepFormula,
+}
+
+fn default_unit() -> String {
+    String::from("reps")
+}
+
+impl Rule {
+    fn matches(&self, intensity: u32) -> bool {
+        intensity >= self.min_intensity && self.max_intensity.is_none_or(|max| intensity < max)
+    }
+}
+
+impl WorkoutPlan {
+    pub fn parse(contents: &str) -> Result<WorkoutPlan, Box<dyn Error>> {
+        let plan: WorkoutPlan = toml::from_str(contents)?;
+
+        for rule in &plan.rules {
+            if let Some(max) = rule.max_intensity {
+                if max <= rule.min_intensity {
+                    return Err(format!("empty intensity range {}..{}", rule.min_intensity, max).into());
+                }
+            }
+        }
+
+        Ok(plan)
+    }
+
+    pub fn from_file(path: &Path) -> Result<WorkoutPlan, Box<dyn Error>> {
+        WorkoutPlan::parse(&fs::read_to_string(path)?)
+    }
+
+    // `expensive_result` is only called when the matching rule has exercises
+    pub fn generate<F>(&self, intensity: u32, random_number: u32, mut expensive_result: F) -> Workout
+        where F: FnMut(u32) -> u32 {
+        // an intensity no rule covers has nothing planned, which is a rest day
+        let rule = match self.rules.iter().find(|r| r.matches(intensity)) {
+            Some(rule) => rule,
+            None => return Workout::Rest,
+        };
+
+        if rule.rest_on == Some(random_number) || rule.exercises.is_empty() {
+            return Workout::Rest;
+        }
+
+        Workout::Exercises(
+            rule.exercises
+                .iter()
+                .map(|e| Exercise {
+                    name: e.name.clone(),
+                    amount: e.amount.apply(expensive_result(intensity)),
+                    unit: e.unit.clone(),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Default for WorkoutPlan {
+    fn default() -> WorkoutPlan {
+        WorkoutPlan::parse(DEFAULT_PLAN).expect("the bundled workout_plan.toml is valid")
+    }
+}
+
+pub fn render(workout: &Workout) -> String {
+    match workout {
+        Workout::Rest => String::from("Take a break, remember to stay hydrated!\n"),
+        Workout::Exercises(exercises) => {
+            let mut text = String::new();
+            for (i, exercise) in exercises.iter().enumerate() {
+                let lead = if i == 0 { "Today" } else { "Next" };
+                text.push_str(&format!("{}, do {}.\n", lead, describe(exercise)));
+            }
+            text
+        }
+    }
+}
+
+pub(crate) fn describe(exercise: &Exercise) -> String {
+    if exercise.unit == "reps" {
+        format!("{} {}", exercise.amount, exercise.name)
+    } else {
+        format!("{} {} of {}", exercise.amount, exercise.unit, exercise.name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+// A formula such as "x / 2 + 5", kept as the list of steps applied to `x`.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "String")]
+struct RepFormula {
+    steps: Vec<(Op, u32)>,
+}
+
+impl RepFormula {
+    fn apply(&self, x: u32) -> u32 {
+        self.steps.iter().fold(x, |acc, &(op, n)| match op {
+            Op::Add => acc.saturating_add(n),
+            Op::Sub => acc.saturating_sub(n),
+            Op::Mul => acc.saturating_mul(n),
+            Op::Div => acc / n,
+        })
+    }
+}
+
+impl TryFrom<String> for RepFormula {
+    type Error = String;
+
+    fn try_from(formula: String) -> Result<RepFormula, String> {
+        let compact: String = formula.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut rest = match compact.strip_prefix('x') {
+            Some(rest) => rest,
+            None => return Err(format!("formula `{}` must start with x", formula)),
+        };
+
+        let mut steps = vec![];
+        while let Some(c) = rest.chars().next() {
+            let op = match c {
+                '+' => Op::Add,
+                '-' => Op::Sub,
+                '*' => Op::Mul,
+                '/' => Op::Div,
+                _ => return Err(format!("unexpected `{}` in formula `{}`", c, formula)),
+            };
+            rest = &rest[1..];
+
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let n: u32 = match rest[..digits].parse() {
+                Ok(n) => n,
+                Err(_) => return Err(format!("expected a number after `{}` in formula `{}`", c, formula)),
+            };
+            if op == Op::Div && n == 0 {
+                return Err(format!("division by zero in formula `{}`", formula));
+            }
+            rest = &rest[digits..];
+
+            steps.push((op, n));
+        }
+
+        Ok(RepFormula { steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formula(s: &str) -> Result<RepFormula, String> {
+        RepFormula::try_from(String::from(s))
+    }
+
+    #[test]
+    fn formulas_apply_left_to_right() {
+        assert_eq!(formula("x").unwrap().apply(10), 10);
+        assert_eq!(formula("x * 2").unwrap().apply(10), 20);
+        assert_eq!(formula("x / 2 + 5").unwrap().apply(10), 10);
+        assert_eq!(formula("x-20").unwrap().apply(10), 0);
+    }
+
+    #[test]
+    fn rejects_bad_formulas() {
+        assert!(formula("2 * x").is_err());
+        assert!(formula("x ^ 2").is_err());
+        assert!(formula("x +").is_err());
+        assert!(formula("x / 0").is_err());
+    }
+
+    #[test]
+    fn default_plan_matches_the_original_workout() {
+        let plan = WorkoutPlan::default();
+
+        assert_eq!(
+            plan.generate(10, 7, |x| x),
+            Workout::Exercises(vec![
+                Exercise { name: String::from("pushups"), amount: 10, unit: String::from("reps") },
+                Exercise { name: String::from("situps"), amount: 10, unit: String::from("reps") },
+            ])
+        );
+        assert_eq!(plan.generate(30, 3, |x| x), Workout::Rest);
+        assert_eq!(
+            plan.generate(30, 7, |x| x),
+            Workout::Exercises(vec![
+                Exercise { name: String::from("running"), amount: 30, unit: String::from("minutes") },
+            ])
+        );
+    }
+
+    #[test]
+    fn rest_days_skip_the_expensive_calculation() {
+        let plan = WorkoutPlan::default();
+        plan.generate(30, 3, |_| panic!("should not be calculated"));
+    }
+
+    #[test]
+    fn loads_custom_rules() {
+        let plan = WorkoutPlan::parse(r#"
+            [[rule]]
+            max_intensity = 50
+            exercises = [{ name = "squats", amount = "x * 3" }]
+
+            [[rule]]
+            min_intensity = 50
+            exercises = [{ name = "cycling", unit = "km", amount = "x / 5" }]
+        "#).unwrap();
+
+        assert_eq!(render(&plan.generate(10, 0, |x| x)), "Today, do 30 squats.\n");
+        assert_eq!(render(&plan.generate(60, 0, |x| x)), "Today, do 12 km of cycling.\n");
+    }
+
+    #[test]
+    fn rejects_bad_plans() {
+        assert!(WorkoutPlan::parse("[[rule]]\nmin_intensity = 10\nmax_intensity = 10\n").is_err());
+        assert!(WorkoutPlan::parse("[[rule]]\nexercises = [{ name = \"squats\", amount = \"y\" }]\n").is_err());
+        assert!(WorkoutPlan::parse("[[rule]]\nintensity = 10\n").is_err());
+    }
+
+    #[test]
+    fn renders_each_exercise_on_its_own_line() {
+        let workout = WorkoutPlan::default().generate(10, 7, |x| x);
+
+        assert_eq!(render(&workout), "Today, do 10 pushups.\nNext, do 10 situps.\n");
+        assert_eq!(render(&Workout::Rest), "Take a break, remember to stay hydrated!\n");
+    }
+}
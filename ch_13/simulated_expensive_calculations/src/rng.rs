@@ -0,0 +1,62 @@
+// Anything that can hand out random numbers. Workouts only take their
+// randomness from here so that a seeded source makes them reproducible.
+pub trait RandomSource {
+    fn next_u32(&mut self) -> u32;
+
+    // a number in `0..bound`; `bound` must not be zero
+    fn below(&mut self, bound: u32) -> u32 {
+        ((self.next_u32() as u64 * bound as u64) >> 32) as u32
+    }
+}
+
+// SplitMix64: tiny, fast, and the same seed gives the same sequence on
+// every platform.
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng { state: seed }
+    }
+}
+
+impl RandomSource for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        ((z ^ (z >> 31)) >> 32) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = SeededRng::new(42);
+        let mut b = SeededRng::new(42);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+    }
+
+    #[test]
+    fn different_seeds_differ() {
+        let a: Vec<u32> = (0..8).scan(SeededRng::new(1), |r, _| Some(r.next_u32())).collect();
+        let b: Vec<u32> = (0..8).scan(SeededRng::new(2), |r, _| Some(r.next_u32())).collect();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn below_stays_in_bounds() {
+        let mut r = SeededRng::new(7);
+        for _ in 0..1000 {
+            assert!(r.below(10) < 10);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::plan::{render, Workout, WorkoutPlan};
+use crate::rng::RandomSource;
+
+pub const DAYS_PER_WEEK: usize = 7;
+const REST_DAYS_PER_WEEK: usize = 2;
+// every training session is this much harder than the one before it
+const OVERLOAD_PERCENT_PER_SESSION: u32 = 5;
+// the random number handed to the plan each day, same as the `rest_on` range
+const RANDOM_NUMBER_RANGE: u32 = 10;
+
+impl WorkoutPlan {
+    // Seven days of workouts. `REST_DAYS_PER_WEEK` days are picked as rest
+    // days up front and the plan's own `rest_on` rules may add more. Each
+    // session after the first gets `OVERLOAD_PERCENT_PER_SESSION` more work.
+    pub fn generate_week<R, F>(&self, intensity: u32, rng: &mut R, mut expensive_result: F) -> Vec<Workout>
+        where R: RandomSource, F: FnMut(u32) -> u32 {
+        let mut days: Vec<usize> = (0..DAYS_PER_WEEK).collect();
+        for i in 0..REST_DAYS_PER_WEEK {
+            let j = i + rng.below((DAYS_PER_WEEK - i) as u32) as usize;
+            days.swap(i, j);
+        }
+        let rest_days = &days[..REST_DAYS_PER_WEEK];
+
+        let mut sessions = 0;
+        (0..DAYS_PER_WEEK)
+            .map(|day| {
+                // always draw, so a day's number doesn't depend on the rest days
+                let random_number = rng.below(RANDOM_NUMBER_RANGE);
+                if rest_days.contains(&day) {
+                    return Workout::Rest;
+                }
+
+                match self.generate(intensity, random_number, &mut expensive_result) {
+                    Workout::Rest => Workout::Rest,
+                    Workout::Exercises(mut exercises) => {
+                        let percent = 100 + OVERLOAD_PERCENT_PER_SESSION * sessions;
+                        for exercise in &mut exercises {
+                            exercise.amount = exercise.amount.saturating_mul(percent) / 100;
+                        }
+                        sessions += 1;
+                        Workout::Exercises(exercises)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn render_week(week: &[Workout]) -> String {
+    let mut text = String::new();
+    for (day, workout) in week.iter().enumerate() {
+        text.push_str(&format!("Day {}:\n", day + 1));
+        for line in render(workout).lines() {
+            text.push_str(&format!("  {}\n", line));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SeededRng;
+
+    fn week(intensity: u32, seed: u64) -> Vec<Workout> {
+        WorkoutPlan::default().generate_week(intensity, &mut SeededRng::new(seed), |x| x)
+    }
+
+    fn amounts(week: &[Workout]) -> Vec<u32> {
+        week.iter()
+            .filter_map(|w| match w {
+                Workout::Exercises(exercises) => Some(exercises[0].amount),
+                Workout::Rest => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_week() {
+        assert_eq!(week(10, 1234), week(10, 1234));
+        assert_eq!(week(40, 99), week(40, 99));
+    }
+
+    #[test]
+    fn seed_changes_the_week() {
+        let weeks: Vec<Vec<Workout>> = (0..10).map(|seed| week(40, seed)).collect();
+        assert!(weeks.iter().any(|w| *w != weeks[0]));
+    }
+
+    #[test]
+    fn has_seven_days_with_rest() {
+        for seed in 0..50 {
+            let w = week(10, seed);
+            assert_eq!(w.len(), DAYS_PER_WEEK);
+            assert!(w.iter().filter(|d| **d == Workout::Rest).count() >= REST_DAYS_PER_WEEK);
+        }
+    }
+
+    #[test]
+    fn sessions_get_harder() {
+        let a = amounts(&week(20, 5));
+        assert_eq!(a.len(), DAYS_PER_WEEK - REST_DAYS_PER_WEEK);
+        assert_eq!(a, vec![20, 21, 22, 23, 24]);
+    }
+
+    #[test]
+    fn renders_days_in_order() {
+        let text = render_week(&[Workout::Rest, Workout::Rest]);
+        assert_eq!(text, "Day 1:\n  Take a break, remember to stay hydrated!\n\
+                          Day 2:\n  Take a break, remember to stay hydrated!\n");
+    }
+}
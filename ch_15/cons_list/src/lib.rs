@@ -1,8 +1,250 @@
+use std::fmt;
 use std::ops::Deref;
 
-pub enum List {
-    Cons(i32, Box<List>),
-    Nil,
+// A singly linked list: each cell owns the next through a `Box`, like the
+// book's `Cons(i32, Box<List>)`. The cells are kept behind a struct rather
+// than being the `List` enum itself, since a type with its own `Drop` can't
+// have its values moved out by a `match`, and `pop_front` needs to.
+pub struct List<T> {
+    head: Link<T>,
+    len: usize,
+}
+
+type Link<T> = Option<Box<Cons<T>>>;
+
+struct Cons<T> {
+    value: T,
+    next: Link<T>,
+}
+
+impl<T> List<T> {
+    pub fn new() -> List<T> {
+        List { head: None, len: 0 }
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        let next = self.head.take();
+        self.head = Some(Box::new(Cons { value, next }));
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let cons = self.head.take()?;
+        self.head = cons.next;
+        self.len -= 1;
+        Some(cons.value)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.as_ref().map(|cons| &cons.value)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.head.as_mut().map(|cons| &mut cons.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Reverses the list in place by turning each link around; nothing is
+    // allocated or moved.
+    pub fn reverse(&mut self) {
+        let mut reversed = None;
+        let mut rest = self.head.take();
+        while let Some(mut cons) = rest {
+            rest = cons.next.take();
+            cons.next = reversed;
+            reversed = Some(cons);
+        }
+        self.head = reversed;
+    }
+
+    // Moves every value of `other` onto the end of this list, leaving `other`
+    // empty. Walks this list to find its end, but doesn't touch `other`'s.
+    pub fn append(&mut self, other: &mut List<T>) {
+        *self.end() = other.head.take();
+        self.len += other.len;
+        other.len = 0;
+    }
+
+    // the empty link after the last value
+    fn end(&mut self) -> &mut Link<T> {
+        let mut end = &mut self.head;
+        while let Some(cons) = end {
+            end = &mut cons.next;
+        }
+        end
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { next: self.head.as_deref(), len: self.len }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut { next: self.head.as_deref_mut(), len: self.len }
+    }
+}
+
+// Letting each `Box` drop the next would recurse once per value, and a long
+// enough list would overflow the stack; unlinking them in a loop doesn't.
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        let mut rest = self.head.take();
+        while let Some(mut cons) = rest {
+            rest = cons.next.take();
+        }
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> List<T> {
+        List::new()
+    }
+}
+
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> List<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &List<T>) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// `1 -> 2 -> 3 -> Nil`
+impl<T: fmt::Display> fmt::Display for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for value in self.iter() {
+            write!(f, "{} -> ", value)?;
+        }
+        write!(f, "Nil")
+    }
+}
+
+// in iteration order, so the first value yielded ends up at the front
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> List<T> {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+// adds the values at the end, in order, like `Vec::extend`
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut end = self.end();
+        let mut added = 0;
+        for value in iter {
+            let cons = end.insert(Box::new(Cons { value, next: None }));
+            end = &mut cons.next;
+            added += 1;
+        }
+        self.len += added;
+    }
+}
+
+pub struct Iter<'a, T> {
+    next: Option<&'a Cons<T>>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let cons = self.next?;
+        self.next = cons.next.as_deref();
+        self.len -= 1;
+        Some(&cons.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+pub struct IterMut<'a, T> {
+    next: Option<&'a mut Cons<T>>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        let cons = self.next.take()?;
+        self.next = cons.next.as_deref_mut();
+        self.len -= 1;
+        Some(&mut cons.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+pub struct IntoIter<T>(List<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
 }
 
 pub struct MyBox<T>(T);
@@ -20,3 +262,90 @@ impl<T> Deref for MyBox<T> {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_pop_at_the_front() {
+        let mut list = List::new();
+        assert_eq!(list.pop_front(), None);
+
+        list.push_front(1);
+        list.push_front(2);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.front(), Some(&2));
+
+        *list.front_mut().unwrap() = 20;
+        assert_eq!(list.pop_front(), Some(20));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn collects_in_order_and_extends_at_the_end() {
+        let mut list: List<i32> = (1..=3).collect();
+        list.extend(vec![4, 5]);
+
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(list.iter().len(), 5);
+    }
+
+    #[test]
+    fn reverse_and_append() {
+        let mut list: List<i32> = (1..=3).collect();
+        list.reverse();
+        assert_eq!(list, (1..=3).rev().collect());
+
+        let mut other: List<i32> = (4..=5).collect();
+        list.append(&mut other);
+        assert_eq!(list, vec![3, 2, 1, 4, 5].into_iter().collect());
+        assert_eq!(list.len(), 5);
+        assert!(other.is_empty());
+
+        let mut empty = List::new();
+        empty.append(&mut list);
+        assert_eq!(empty.len(), 5);
+        empty.reverse();
+        assert_eq!(empty.front(), Some(&5));
+    }
+
+    #[test]
+    fn iterates_by_reference_by_mut_and_by_value() {
+        let mut list: List<String> = ["a", "b"].iter().map(|s| s.to_string()).collect();
+
+        for value in &mut list {
+            value.push('!');
+        }
+        let borrowed: Vec<&str> = (&list).into_iter().map(|s| s.as_str()).collect();
+        assert_eq!(borrowed, vec!["a!", "b!"]);
+
+        let owned: Vec<String> = list.into_iter().collect();
+        assert_eq!(owned, vec!["a!", "b!"]);
+    }
+
+    #[test]
+    fn clone_eq_and_formatting() {
+        let list: List<i32> = (1..=3).collect();
+        let copy = list.clone();
+        assert_eq!(list, copy);
+        assert_ne!(list, (1..=2).collect());
+        assert_ne!(list, (1..=4).collect());
+
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+        assert_eq!(list.to_string(), "1 -> 2 -> 3 -> Nil");
+        assert_eq!(List::<i32>::new().to_string(), "Nil");
+    }
+
+    #[test]
+    fn long_lists_drop_without_overflowing() {
+        let list: List<u32> = (0..1_000_000).collect();
+        let copy = list.clone();
+        assert_eq!(list, copy);
+        drop(list);
+        drop(copy);
+    }
+}
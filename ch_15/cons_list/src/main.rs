@@ -1,12 +1,13 @@
-use cons_list::List::{Cons, Nil};
-use cons_list::MyBox;
+use cons_list::{List, MyBox};
 use std::ops::Deref;
 
 fn main() {
     println!("Hello world");
-    let _list = Box::new(Cons(1, Box::new(Cons(2, Box::new
-        (Cons(3, Box::new(Nil))))
-    )));
+    let mut list = List::new();
+    list.push_front(3);
+    list.push_front(2);
+    list.push_front(1);
+    println!("{}", list);
 
     // Deref section 15.2
     let a = 5;
@@ -1,5 +1,9 @@
 use std::rc::Rc;
 
+mod plist;
+
+pub use plist::{PList, PListIter};
+
 pub enum List {
     Cons(i32, Rc<List>),
     Nil,
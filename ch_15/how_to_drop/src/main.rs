@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use how_to_drop::CustomSmartPointer;
+use how_to_drop::{CustomSmartPointer, PList};
 use how_to_drop::List::{Cons, Nil};
 
 fn main() {
@@ -20,4 +20,8 @@ fn main() {
         println!("Counting after creating d {}", Rc::strong_count(&a));
     }
     println!("Counting after dropping d {}", Rc::strong_count(&a));
+
+    let first = PList::new().cons(10).cons(5);
+    let second = first.cons(3);
+    println!("{:?} shares its tail with {:?}", second, first);
 }
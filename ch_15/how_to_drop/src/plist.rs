@@ -0,0 +1,200 @@
+use std::fmt;
+use std::rc::Rc;
+
+// A persistent list: it never changes once built. `cons` makes a new list
+// whose tail is the old one, shared through an `Rc` rather than copied, so
+// keeping every version of a list costs one cell per version.
+pub struct PList<T> {
+    head: Option<Rc<PCons<T>>>,
+    len: usize,
+}
+
+struct PCons<T> {
+    value: T,
+    next: Option<Rc<PCons<T>>>,
+}
+
+impl<T> PList<T> {
+    pub fn new() -> PList<T> {
+        PList { head: None, len: 0 }
+    }
+
+    // a list of `value` followed by this one, which is left as it was
+    pub fn cons(&self, value: T) -> PList<T> {
+        PList {
+            head: Some(Rc::new(PCons { value, next: self.head.clone() })),
+            len: self.len + 1,
+        }
+    }
+
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|cons| &cons.value)
+    }
+
+    // everything after the head, sharing its cells with this list; `None`
+    // for the empty list
+    pub fn tail(&self) -> Option<PList<T>> {
+        self.head.as_ref().map(|cons| PList { head: cons.next.clone(), len: self.len - 1 })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // whether both are the very same cells, not just equal values
+    pub fn ptr_eq(&self, other: &PList<T>) -> bool {
+        match (&self.head, &other.head) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    pub fn iter(&self) -> PListIter<'_, T> {
+        PListIter { next: self.head.as_deref() }
+    }
+}
+
+// Only the cells no other list shares are freed, one at a time; the first
+// shared one stops the loop, since its other owners still need the rest.
+impl<T> Drop for PList<T> {
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(cons) = next {
+            next = match Rc::try_unwrap(cons) {
+                Ok(mut cons) => cons.next.take(),
+                Err(_) => break,
+            };
+        }
+    }
+}
+
+// another handle to the same cells, whatever `T` is
+impl<T> Clone for PList<T> {
+    fn clone(&self) -> PList<T> {
+        PList { head: self.head.clone(), len: self.len }
+    }
+}
+
+impl<T> Default for PList<T> {
+    fn default() -> PList<T> {
+        PList::new()
+    }
+}
+
+impl<T: PartialEq> PartialEq for PList<T> {
+    fn eq(&self, other: &PList<T>) -> bool {
+        self.len == other.len && (self.ptr_eq(other) || self.iter().eq(other.iter()))
+    }
+}
+
+impl<T: Eq> Eq for PList<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for PList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// in iteration order, so the first value yielded is the head
+impl<T> FromIterator<T> for PList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> PList<T> {
+        let values: Vec<T> = iter.into_iter().collect();
+        values.into_iter().rev().fold(PList::new(), |list, value| list.cons(value))
+    }
+}
+
+pub struct PListIter<'a, T> {
+    next: Option<&'a PCons<T>>,
+}
+
+impl<'a, T> Iterator for PListIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let cons = self.next?;
+        self.next = cons.next.as_deref();
+        Some(&cons.value)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PList<T> {
+    type Item = &'a T;
+    type IntoIter = PListIter<'a, T>;
+
+    fn into_iter(self) -> PListIter<'a, T> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cons_shares_the_tail() {
+        let empty = PList::new();
+        let one = empty.cons(1);
+        let two = one.cons(2);
+
+        assert_eq!(two.head(), Some(&2));
+        assert_eq!(two.len(), 2);
+        assert!(two.tail().unwrap().ptr_eq(&one));
+        assert!(one.tail().unwrap().ptr_eq(&empty));
+        assert!(empty.tail().is_none());
+
+        // the older versions are untouched
+        assert_eq!(one, PList::from_iter([1]));
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn keeps_an_undo_history() {
+        let mut history = vec![PList::new()];
+        for edit in ["type a", "type b", "delete b"] {
+            let latest = history.last().unwrap().cons(edit);
+            history.push(latest);
+        }
+
+        assert_eq!(history[3].iter().copied().collect::<Vec<_>>(), vec!["delete b", "type b", "type a"]);
+        for version in 1..history.len() {
+            assert!(history[version].tail().unwrap().ptr_eq(&history[version - 1]));
+        }
+
+        // undoing is dropping the newest version
+        history.pop();
+        assert_eq!(history.last().unwrap().head(), Some(&"type b"));
+    }
+
+    #[test]
+    fn clones_and_compares() {
+        let list: PList<i32> = (1..=3).collect();
+        let copy = list.clone();
+        assert!(copy.ptr_eq(&list));
+        assert_eq!(copy, list);
+
+        // equal values in different cells
+        let rebuilt: PList<i32> = (1..=3).collect();
+        assert!(!rebuilt.ptr_eq(&list));
+        assert_eq!(rebuilt, list);
+        assert_ne!(list.tail().unwrap(), list);
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn long_lists_drop_without_overflowing() {
+        let shared: PList<u32> = (0..500_000).collect();
+        let longer = (0..500_000).fold(shared.clone(), |list, i| list.cons(i));
+        assert_eq!(longer.len(), 1_000_000);
+
+        // dropping the longer list stops where it starts sharing
+        drop(longer);
+        assert_eq!(shared.len(), 500_000);
+        assert_eq!(shared.iter().last(), Some(&499_999));
+        drop(shared);
+    }
+}
@@ -0,0 +1,158 @@
+use std::future::Future;
+
+use futures::executor;
+
+use crate::{Messenger, Notification, QuotaPolicy, SendError, Watcher, DEFAULT_TRACKER_ID};
+
+// The async counterpart of `Messenger`, for transports that are async
+// anyway. The future doesn't have to be `Send`; wrap the messenger in
+// something that is if your runtime needs it.
+pub trait AsyncMessenger {
+    fn send(&self, notification: &Notification) -> impl Future<Output = Result<(), SendError>>;
+}
+
+impl<M: AsyncMessenger + ?Sized> AsyncMessenger for &M {
+    fn send(&self, notification: &Notification) -> impl Future<Output = Result<(), SendError>> {
+        (**self).send(notification)
+    }
+}
+
+// Uses a blocking `Messenger` where an `AsyncMessenger` is wanted. The send
+// runs inside the first poll, so it blocks whatever is polling; that's fine
+// for quick messengers like `StderrMessenger` but holds up an executor
+// thread for slow ones.
+pub struct AsAsync<M>(pub M);
+
+impl<M: Messenger> AsyncMessenger for AsAsync<M> {
+    async fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        self.0.send(notification)
+    }
+}
+
+// Uses an `AsyncMessenger` where a blocking `Messenger` is wanted, by running
+// each send to completion on the calling thread. Don't use it from inside an
+// async task: it would block the executor the task runs on.
+pub struct BlockOn<M>(pub M);
+
+impl<M: AsyncMessenger> Messenger for BlockOn<M> {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        executor::block_on(self.0.send(notification))
+    }
+}
+
+// `LimitTracker` for an `AsyncMessenger`: `set_value` finishes once the
+// notification it causes has been delivered.
+pub struct AsyncLimitTracker<'a, T: AsyncMessenger> {
+    messenger: &'a T,
+    value: usize,
+    watcher: Watcher,
+}
+
+impl<'a, T> AsyncLimitTracker<'a, T> where T: AsyncMessenger {
+    pub fn new(messenger: &'a T, max: usize) -> AsyncLimitTracker<'a, T> {
+        AsyncLimitTracker::with_policy(messenger, max, QuotaPolicy::default())
+    }
+
+    pub fn with_policy(messenger: &'a T, max: usize, policy: QuotaPolicy) -> AsyncLimitTracker<'a, T> {
+        AsyncLimitTracker {
+            messenger,
+            value: 0,
+            watcher: Watcher::new(DEFAULT_TRACKER_ID, max, policy),
+        }
+    }
+
+    pub fn with_id(mut self, id: &str) -> AsyncLimitTracker<'a, T> {
+        self.watcher.id = String::from(id);
+        self
+    }
+
+    pub fn value(&self) -> usize {
+        self.value
+    }
+
+    // As with `LimitTracker::set_value`, a failed notification is handed back
+    // and tried again on the next call. If the future is dropped before the
+    // send finishes, the value is kept but the notification counts as unsent.
+    pub async fn set_value(&mut self, value: usize) -> Result<(), SendError> {
+        self.value = value;
+        let (level, due) = self.watcher.check(value);
+        if let Some(notification) = &due {
+            self.messenger.send(notification).await?;
+        }
+        self.watcher.reached = level;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockMessenger;
+    use crate::LimitTracker;
+    use futures::future::{self, Either};
+    use std::pin::pin;
+
+    #[test]
+    fn awaits_delivery() {
+        let messenger = MockMessenger::new().pending_polls(3);
+        let mut tracker = AsyncLimitTracker::new(&messenger, 100).with_id("user-42");
+
+        executor::block_on(tracker.set_value(80)).unwrap();
+        assert_eq!(messenger.messages(), vec!["Warning: You've used up over 75% of your quota."]);
+        assert_eq!(messenger.notifications()[0].tracker_id, "user-42");
+
+        messenger.clear();
+        executor::block_on(tracker.set_value(50)).unwrap();
+        assert_eq!(messenger.messages(), vec!["Good news: you're back under 75% of your quota."]);
+    }
+
+    #[test]
+    fn retries_a_failed_send() {
+        let messenger = MockMessenger::new().failing(1);
+        let mut tracker = AsyncLimitTracker::new(&messenger, 100);
+
+        assert!(executor::block_on(tracker.set_value(95)).is_err());
+        assert!(messenger.messages().is_empty());
+
+        executor::block_on(tracker.set_value(96)).unwrap();
+        assert_eq!(messenger.messages(), vec!["Urgent warning: You've used up over 90% of your quota"]);
+    }
+
+    #[test]
+    fn a_dropped_send_is_tried_again() {
+        let messenger = MockMessenger::new().pending_polls(10);
+        let mut tracker = AsyncLimitTracker::new(&messenger, 100);
+
+        // give up on the first send before it finishes
+        executor::block_on(async {
+            let set = pin!(tracker.set_value(80));
+            match future::select(set, future::ready(())).await {
+                Either::Left(_) => panic!("the send should still be pending"),
+                Either::Right(_) => {}
+            }
+        });
+        assert_eq!(tracker.value(), 80);
+        assert!(messenger.messages().is_empty());
+
+        executor::block_on(tracker.set_value(80)).unwrap();
+        assert_eq!(messenger.messages().len(), 1);
+    }
+
+    #[test]
+    fn adapts_a_sync_messenger() {
+        let messenger = AsAsync(MockMessenger::new());
+        let mut tracker = AsyncLimitTracker::new(&messenger, 10);
+
+        executor::block_on(tracker.set_value(10)).unwrap();
+        assert_eq!(messenger.0.messages(), vec!["You're over your quota."]);
+    }
+
+    #[test]
+    fn adapts_an_async_messenger() {
+        let messenger = BlockOn(MockMessenger::new().pending_polls(2));
+        let mut tracker = LimitTracker::new(&messenger, 10);
+
+        tracker.set_value(8).unwrap();
+        assert_eq!(messenger.0.messages(), vec!["Warning: You've used up over 75% of your quota."]);
+    }
+}
@@ -0,0 +1,22 @@
+// Ready-made `Messenger`s. Each one sits behind the cargo feature of the same
+// name so you only pay for the dependencies of the ones you use.
+
+#[cfg(feature = "file")]
+mod file;
+#[cfg(feature = "smtp")]
+mod smtp;
+#[cfg(feature = "stderr")]
+mod stderr;
+#[cfg(all(test, any(feature = "webhook", feature = "smtp")))]
+mod stub;
+#[cfg(feature = "webhook")]
+mod webhook;
+
+#[cfg(feature = "file")]
+pub use file::FileMessenger;
+#[cfg(feature = "smtp")]
+pub use smtp::SmtpMessenger;
+#[cfg(feature = "stderr")]
+pub use stderr::StderrMessenger;
+#[cfg(feature = "webhook")]
+pub use webhook::WebhookMessenger;
@@ -0,0 +1,67 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{Messenger, Notification, SendError};
+
+// Appends every notification to a file as one JSON object per line. The file
+// is opened for each notification, so it can be rotated or tailed freely.
+#[derive(Clone, Debug)]
+pub struct FileMessenger {
+    path: PathBuf,
+}
+
+impl FileMessenger {
+    pub fn new(path: &Path) -> FileMessenger {
+        FileMessenger {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Messenger for FileMessenger {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        let mut line = notification.to_json().to_string();
+        line.push('\n');
+
+        // a single write per line keeps lines from concurrent writers whole
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::tests::notification;
+    use std::{env, fs, process};
+
+    #[test]
+    fn appends_json_lines() {
+        let path = env::temp_dir().join(format!("file_messenger_{}.jsonl", process::id()));
+        let _ = fs::remove_file(&path);
+        let messenger = FileMessenger::new(&path);
+
+        messenger.send(&notification()).unwrap();
+        messenger.send(&notification()).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let json: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(json["tracker_id"], "api-key-7");
+        assert_eq!(json["severity"], "urgent");
+        assert_eq!(json["kind"], "reached");
+        assert_eq!(json["value"], 93);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reports_unwritable_paths() {
+        let messenger = FileMessenger::new(Path::new("/no/such/directory/notifications.jsonl"));
+        assert!(messenger.send(&notification()).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use std::error::Error;
+
+use lettre::message::Mailbox;
+use lettre::transport::smtp::SmtpTransport;
+use lettre::{Message, Transport};
+
+use crate::{Messenger, Notification, SendError};
+
+// Emails each notification to every address in `to`.
+#[derive(Clone)]
+pub struct SmtpMessenger {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl SmtpMessenger {
+    // sends through `relay` over TLS on the submission port
+    pub fn relay(relay: &str, from: &str, to: &[&str]) -> Result<SmtpMessenger, Box<dyn Error + Send + Sync>> {
+        SmtpMessenger::with_transport(SmtpTransport::relay(relay)?.build(), from, to)
+    }
+
+    // for any transport, e.g. one with credentials or a plain-text local server
+    pub fn with_transport(transport: SmtpTransport, from: &str, to: &[&str]) -> Result<SmtpMessenger, Box<dyn Error + Send + Sync>> {
+        Ok(SmtpMessenger {
+            transport,
+            from: from.parse()?,
+            to: to.iter().map(|address| address.parse()).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl Messenger for SmtpMessenger {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        let mut email = Message::builder()
+            .from(self.from.clone())
+            .subject(format!("[{}] quota notification for {}", notification.severity, notification.tracker_id));
+        for to in &self.to {
+            email = email.to(to.clone());
+        }
+
+        self.transport.send(&email.body(notification.to_string())?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::stub;
+    use crate::notification::tests::notification;
+
+    fn messenger(port: u16) -> SmtpMessenger {
+        let transport = SmtpTransport::builder_dangerous("127.0.0.1").port(port).build();
+        SmtpMessenger::with_transport(transport, "quota@example.com", &["ops@example.com", "dev@example.com"])
+            .unwrap()
+    }
+
+    #[test]
+    fn emails_every_recipient() {
+        let (addr, mails) = stub::smtp();
+
+        messenger(addr.port()).send(&notification()).unwrap();
+
+        let mail = mails.recv().unwrap();
+        assert!(mail.contains("MAIL FROM:<quota@example.com>"));
+        assert!(mail.contains("RCPT TO:<ops@example.com>"));
+        assert!(mail.contains("RCPT TO:<dev@example.com>"));
+        assert!(mail.contains("Subject: [urgent] quota notification for api-key-7"));
+        assert!(mail.contains("[urgent] api-key-7: Almost out (93/100, 93%)"));
+    }
+
+    #[test]
+    fn fails_when_nobody_listens() {
+        let addr = stub::closed_port();
+        assert!(messenger(addr.port()).send(&notification()).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_addresses() {
+        let transport = SmtpTransport::builder_dangerous("127.0.0.1").build();
+        assert!(SmtpMessenger::with_transport(transport, "not an address", &[]).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+use std::io::{self, Write};
+
+use crate::{Messenger, Notification, SendError};
+
+// Prints each notification's text on its own line on stderr.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StderrMessenger;
+
+impl StderrMessenger {
+    pub fn new() -> StderrMessenger {
+        StderrMessenger
+    }
+}
+
+impl Messenger for StderrMessenger {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        writeln!(io::stderr().lock(), "{}", notification)?;
+        Ok(())
+    }
+}
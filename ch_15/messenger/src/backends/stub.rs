@@ -0,0 +1,104 @@
+// Tiny single-connection servers for testing the network backends without
+// leaving the machine. Each one hands back what it received over a channel.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+// answers one HTTP request with `status` and passes on the raw request
+#[cfg(feature = "webhook")]
+pub fn http(status: u16) -> (SocketAddr, Receiver<String>) {
+    use std::io::Read;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut request = String::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            request.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        request.push_str(&String::from_utf8(body).unwrap());
+
+        let response = format!("HTTP/1.1 {} Stub\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+        reader.get_mut().write_all(response.as_bytes()).unwrap();
+        let _ = tx.send(request);
+    });
+
+    (addr, rx)
+}
+
+// accepts one mail and passes on the whole SMTP conversation from the client
+#[cfg(feature = "smtp")]
+pub fn smtp() -> (SocketAddr, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        stream.write_all(b"220 stub ESMTP\r\n").unwrap();
+
+        let mut conversation = String::new();
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            conversation.push_str(&line);
+
+            let reply: &[u8] = if in_data {
+                if line != ".\r\n" {
+                    continue;
+                }
+                in_data = false;
+                b"250 queued\r\n"
+            } else {
+                match line.get(..4).map(|verb| verb.to_ascii_uppercase()).as_deref() {
+                    Some("EHLO") => b"250-stub\r\n250 8BITMIME\r\n",
+                    Some("DATA") => {
+                        in_data = true;
+                        b"354 go ahead\r\n"
+                    }
+                    Some("QUIT") => {
+                        stream.write_all(b"221 bye\r\n").unwrap();
+                        break;
+                    }
+                    _ => b"250 ok\r\n",
+                }
+            };
+            stream.write_all(reply).unwrap();
+        }
+
+        let _ = tx.send(conversation);
+    });
+
+    (addr, rx)
+}
+
+// an address nothing is listening on
+pub fn closed_port() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
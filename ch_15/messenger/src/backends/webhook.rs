@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use crate::{Messenger, Notification, SendError};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// POSTs each notification as JSON to `url`. Any status outside 2xx counts as
+// a failure.
+#[derive(Clone, Debug)]
+pub struct WebhookMessenger {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl WebhookMessenger {
+    pub fn new(url: &str) -> WebhookMessenger {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(TIMEOUT))
+            .build()
+            .into();
+
+        WebhookMessenger {
+            url: String::from(url),
+            agent,
+        }
+    }
+}
+
+impl Messenger for WebhookMessenger {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        self.agent
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .send(notification.to_json().to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::stub;
+    use crate::notification::tests::notification;
+
+    #[test]
+    fn posts_json_to_the_url() {
+        let (addr, requests) = stub::http(200);
+        let messenger = WebhookMessenger::new(&format!("http://{}/hooks/quota", addr));
+
+        messenger.send(&notification()).unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /hooks/quota HTTP/1.1\r\n"));
+        assert!(request.to_lowercase().contains("content-type: application/json"));
+
+        let body = request.split("\r\n\r\n").nth(1).unwrap();
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["message"], "Almost out");
+        assert_eq!(json["percentage"], 93.0);
+    }
+
+    #[test]
+    fn fails_on_error_status() {
+        let (addr, _requests) = stub::http(500);
+        let messenger = WebhookMessenger::new(&format!("http://{}/", addr));
+
+        assert!(messenger.send(&notification()).is_err());
+    }
+
+    #[test]
+    fn fails_when_nobody_listens() {
+        let addr = stub::closed_port();
+        let messenger = WebhookMessenger::new(&format!("http://{}/", addr));
+
+        assert!(messenger.send(&notification()).is_err());
+    }
+}
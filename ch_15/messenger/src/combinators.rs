@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Messenger, Notification, SendError, Severity};
+
+// Sends every notification to all of its messengers, even when some of them
+// fail. The error lists every failure.
+pub struct FanOut(pub Vec<Box<dyn Messenger>>);
+
+#[derive(Debug)]
+pub struct FanOutError(pub Vec<SendError>);
+
+impl fmt::Display for FanOutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of the fan-out messengers failed", self.0.len())?;
+        for err in &self.0 {
+            write!(f, "; {}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for FanOutError {}
+
+impl Messenger for FanOut {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        let errors: Vec<SendError> = self.0.iter().filter_map(|m| m.send(notification).err()).collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Box::new(FanOutError(errors)))
+        }
+    }
+}
+
+// Tries `messenger` up to `attempts` times, sleeping `backoff` after the first
+// failure and twice as long after each one after that. The last error is
+// the one returned.
+pub struct Retry<M> {
+    pub messenger: M,
+    pub attempts: u32,
+    pub backoff: Duration,
+}
+
+impl<M: Messenger> Retry<M> {
+    pub fn new(messenger: M, attempts: u32, backoff: Duration) -> Retry<M> {
+        Retry {
+            messenger,
+            attempts,
+            backoff,
+        }
+    }
+}
+
+impl<M: Messenger> Messenger for Retry<M> {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        let mut delay = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match self.messenger.send(notification) {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt >= self.attempts => return Err(err),
+                Err(_) => {
+                    thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+// Lets at most one notification per tracker through every `per`; the rest are
+// dropped and count as sent. A notification more severe than the last one let
+// through is never dropped, so an escalation is always heard.
+pub struct RateLimited<M> {
+    messenger: M,
+    per: Duration,
+    last_sent: Mutex<HashMap<String, (Instant, Severity)>>,
+}
+
+impl<M: Messenger> RateLimited<M> {
+    pub fn new(messenger: M, per: Duration) -> RateLimited<M> {
+        RateLimited {
+            messenger,
+            per,
+            last_sent: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<M: Messenger> Messenger for RateLimited<M> {
+    // The tracker's slot is taken before sending and given back if the send
+    // fails, so the lock isn't held while a slow messenger works.
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        let now = Instant::now();
+        let sending = (now, notification.severity);
+        let previous = {
+            let mut last_sent = self.last_sent.lock().unwrap();
+            let previous = last_sent.get(&notification.tracker_id).copied();
+            if let Some((at, severity)) = previous {
+                if now.duration_since(at) < self.per && notification.severity <= severity {
+                    return Ok(());
+                }
+            }
+            last_sent.insert(notification.tracker_id.clone(), sending);
+            previous
+        };
+
+        let sent = self.messenger.send(notification);
+        if sent.is_err() {
+            let mut last_sent = self.last_sent.lock().unwrap();
+            // unless a later send has taken the slot since
+            if last_sent.get(&notification.tracker_id) == Some(&sending) {
+                match previous {
+                    Some(previous) => last_sent.insert(notification.tracker_id.clone(), previous),
+                    None => last_sent.remove(&notification.tracker_id),
+                };
+            }
+        }
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockMessenger;
+    use crate::notification::tests::notification;
+    use std::sync::Arc;
+
+    fn with(tracker_id: &str, severity: Severity) -> Notification {
+        Notification {
+            tracker_id: String::from(tracker_id),
+            severity,
+            ..notification()
+        }
+    }
+
+    #[test]
+    fn fan_out_sends_to_everyone() {
+        let a = Arc::new(MockMessenger::new());
+        let b = Arc::new(MockMessenger::new());
+        let fan_out = FanOut(vec![Box::new(a.clone()), Box::new(b.clone())]);
+
+        fan_out.send(&notification()).unwrap();
+        assert_eq!(a.notifications().len(), 1);
+        assert_eq!(b.notifications().len(), 1);
+    }
+
+    #[test]
+    fn fan_out_keeps_going_after_a_failure() {
+        let ok = Arc::new(MockMessenger::new());
+        let failing = || Box::new(MockMessenger::new().failing(1));
+        let fan_out = FanOut(vec![failing(), Box::new(ok.clone()), failing()]);
+
+        let err = fan_out.send(&notification()).unwrap_err();
+        assert_eq!(ok.notifications().len(), 1);
+        assert_eq!(err.to_string(),
+                   "2 of the fan-out messengers failed; mock messenger told to fail; mock messenger told to fail");
+    }
+
+    #[test]
+    fn retry_until_it_works() {
+        let retry = Retry::new(MockMessenger::new().failing(2), 3, Duration::ZERO);
+
+        retry.send(&notification()).unwrap();
+        assert_eq!(retry.messenger.calls(), 3);
+        assert_eq!(retry.messenger.notifications().len(), 1);
+    }
+
+    #[test]
+    fn retry_gives_up_after_its_attempts() {
+        let retry = Retry::new(MockMessenger::new().failing(5), 3, Duration::ZERO);
+
+        assert!(retry.send(&notification()).is_err());
+        assert_eq!(retry.messenger.calls(), 3);
+    }
+
+    #[test]
+    fn retry_backs_off() {
+        let retry = Retry::new(MockMessenger::new().failing(2), 3, Duration::from_millis(10));
+
+        let start = Instant::now();
+        retry.send(&notification()).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn rate_limited_drops_repeats_per_tracker() {
+        let limited = RateLimited::new(MockMessenger::new(), Duration::from_secs(3600));
+
+        limited.send(&with("a", Severity::Warning)).unwrap();
+        limited.send(&with("a", Severity::Warning)).unwrap();
+        limited.send(&with("a", Severity::Info)).unwrap();
+        limited.send(&with("b", Severity::Warning)).unwrap();
+
+        let trackers: Vec<String> = limited.messenger.notifications().iter().map(|n| n.tracker_id.clone()).collect();
+        assert_eq!(trackers, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn rate_limited_lets_escalations_through() {
+        let limited = RateLimited::new(MockMessenger::new(), Duration::from_secs(3600));
+
+        limited.send(&with("a", Severity::Warning)).unwrap();
+        limited.send(&with("a", Severity::Critical)).unwrap();
+        limited.send(&with("a", Severity::Urgent)).unwrap();
+
+        let severities: Vec<Severity> = limited.messenger.notifications().iter().map(|n| n.severity).collect();
+        assert_eq!(severities, vec![Severity::Warning, Severity::Critical]);
+    }
+
+    #[test]
+    fn rate_limited_sends_again_after_the_period() {
+        let limited = RateLimited::new(MockMessenger::new(), Duration::ZERO);
+
+        limited.send(&notification()).unwrap();
+        limited.send(&notification()).unwrap();
+        assert_eq!(limited.messenger.notifications().len(), 2);
+    }
+
+    #[test]
+    fn rate_limited_does_not_count_failed_sends() {
+        let limited = RateLimited::new(MockMessenger::new().failing(1), Duration::from_secs(3600));
+
+        assert!(limited.send(&notification()).is_err());
+        limited.send(&notification()).unwrap();
+        assert_eq!(limited.messenger.notifications().len(), 1);
+    }
+
+    #[test]
+    fn rate_limited_does_not_block_other_trackers_while_sending() {
+        // the first send fails and sleeps before its retry
+        let inner = Retry::new(MockMessenger::new().failing(1), 2, Duration::from_millis(500));
+        let limited = RateLimited::new(inner, Duration::from_secs(3600));
+
+        thread::scope(|s| {
+            s.spawn(|| limited.send(&with("a", Severity::Warning)).unwrap());
+            thread::sleep(Duration::from_millis(50));
+
+            let start = Instant::now();
+            limited.send(&with("b", Severity::Warning)).unwrap();
+            assert!(start.elapsed() < Duration::from_millis(400));
+        });
+        assert_eq!(limited.messenger.messenger.messages().len(), 2);
+    }
+
+    #[test]
+    fn rate_limited_restores_the_last_send_after_a_failure() {
+        let limited = RateLimited::new(MockMessenger::new(), Duration::from_secs(3600));
+
+        limited.send(&with("a", Severity::Warning)).unwrap();
+        limited.messenger.fail_next(1);
+        assert!(limited.send(&with("a", Severity::Critical)).is_err());
+
+        // the warning still holds back repeats, and the critical can retry
+        limited.send(&with("a", Severity::Warning)).unwrap();
+        limited.send(&with("a", Severity::Critical)).unwrap();
+        let severities: Vec<Severity> = limited.messenger.notifications().iter().map(|n| n.severity).collect();
+        assert_eq!(severities, vec![Severity::Warning, Severity::Critical]);
+    }
+
+    #[test]
+    fn combinators_nest() {
+        let mock = Arc::new(MockMessenger::new().failing(1));
+        let stack = RateLimited::new(FanOut(vec![Box::new(Retry::new(mock.clone(), 2, Duration::ZERO))]),
+                                     Duration::from_secs(3600));
+
+        stack.send(&notification()).unwrap();
+        stack.send(&notification()).unwrap();
+        assert_eq!(mock.calls(), 2);
+        assert_eq!(mock.notifications().len(), 1);
+    }
+}
@@ -1,33 +1,184 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+mod async_messenger;
+pub mod backends;
+mod combinators;
+mod manager;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+mod notification;
+mod policy;
+pub mod stores;
+
+pub use async_messenger::{AsAsync, AsyncLimitTracker, AsyncMessenger, BlockOn};
+pub use combinators::{FanOut, FanOutError, RateLimited, Retry};
+pub use manager::{QuotaManager, Window, DAY, HOUR, MINUTE};
+pub use notification::{Notification, NotificationKind};
+pub use policy::{QuotaPolicy, Severity, Threshold};
+pub use stores::{HistoryQuery, StateStore, StoreError, TrackerState};
+
+const DEFAULT_TRACKER_ID: &str = "default";
+
+pub type SendError = Box<dyn Error + Send + Sync>;
+
 pub trait Messenger {
-    fn send(&self, msg: &str);
+    fn send(&self, notification: &Notification) -> Result<(), SendError>;
+}
+
+impl<M: Messenger + ?Sized> Messenger for &M {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        (**self).send(notification)
+    }
+}
+
+impl<M: Messenger + ?Sized> Messenger for Box<M> {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        (**self).send(notification)
+    }
+}
+
+impl<M: Messenger + ?Sized> Messenger for Arc<M> {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        (**self).send(notification)
+    }
 }
 
 pub struct LimitTracker<'a, T: Messenger> {
     messenger: &'a T,
     value: usize,
-    max: usize,
+    watcher: Watcher,
+    store: Option<&'a dyn StateStore>,
 }
 
 impl<'a, T> LimitTracker<'a, T> where T: Messenger {
-    pub fn new(messenger: &T, max: usize) -> LimitTracker<T> {
+    pub fn new(messenger: &'a T, max: usize) -> LimitTracker<'a, T> {
+        LimitTracker::with_policy(messenger, max, QuotaPolicy::default())
+    }
+
+    pub fn with_policy(messenger: &'a T, max: usize, policy: QuotaPolicy) -> LimitTracker<'a, T> {
         LimitTracker {
             messenger,
             value: 0,
-            max,
+            watcher: Watcher::new(DEFAULT_TRACKER_ID, max, policy),
+            store: None,
+        }
+    }
+
+    // names the tracker in its notifications, e.g. after the user or API key it watches
+    pub fn with_id(mut self, id: &str) -> LimitTracker<'a, T> {
+        self.watcher.id = String::from(id);
+        self
+    }
+
+    // Keeps the tracker's value and warnings in `store` under its id, and
+    // picks up whatever an earlier tracker with that id left there. Call it
+    // after `with_id`. Delivered notifications go into the store's history.
+    // The warnings only hold for the max they were sent at, so a tracker
+    // stored with a different max is an error; remove it to start over.
+    pub fn with_store(mut self, store: &'a dyn StateStore) -> Result<LimitTracker<'a, T>, StoreError> {
+        if let Some(state) = store.load(&self.watcher.id)? {
+            if let Some(max) = state.max.filter(|max| *max != self.watcher.max) {
+                return Err(format!("tracker `{}` was stored with a max of {}, not {}",
+                                   self.watcher.id, max, self.watcher.max).into());
+            }
+            self.value = state.value;
+            self.watcher.reached = state.reached;
         }
+        self.store = Some(store);
+        Ok(self)
     }
 
-    pub fn set_value(&mut self, value: usize) {
+    pub fn value(&self) -> usize {
+        self.value
+    }
+
+    // A failed notification is handed back, and the tracker keeps the
+    // thresholds where they were so the next `set_value` tries again.
+    pub fn set_value(&mut self, value: usize) -> Result<(), SendError> {
         self.value = value;
+        let sent = self.watcher.update(self.messenger, value);
+
+        if let Some(store) = self.store {
+            let state = TrackerState {
+                value,
+                max: Some(self.watcher.max),
+                reached: self.watcher.reached,
+                ..TrackerState::default()
+            };
+            store.save(&self.watcher.id, &state)?;
+            if let Ok(Some(notification)) = &sent {
+                store.record(notification)?;
+            }
+        }
+        sent.map(|_| ())
+    }
+}
+
+// Which of a policy's thresholds one tracker has warned about, and the
+// notifications to send as its usage moves.
+pub(crate) struct Watcher {
+    pub(crate) id: String,
+    pub(crate) max: usize,
+    policy: QuotaPolicy,
+    // how many of the policy's thresholds have already been warned about
+    pub(crate) reached: usize,
+}
+
+impl Watcher {
+    pub(crate) fn new(id: &str, max: usize, policy: QuotaPolicy) -> Watcher {
+        Watcher {
+            id: String::from(id),
+            max,
+            policy,
+            reached: 0,
+        }
+    }
+
+    // returns the notification it sent, if any
+    pub(crate) fn update<M: Messenger + ?Sized>(&mut self, messenger: &M, value: usize)
+        -> Result<Option<Notification>, SendError> {
+        let (level, due) = self.check(value);
+        if let Some(notification) = &due {
+            messenger.send(notification)?;
+        }
+        self.reached = level;
+        Ok(due)
+    }
 
-        let percentage_of_max = self.value as f64 / self.max as f64;
+    // The notification due at `value`, if any, and how many thresholds count
+    // as reached once it's delivered. There's one message per change: the
+    // highest threshold newly reached, or the lowest one we've dropped back under.
+    pub(crate) fn check(&self, value: usize) -> (usize, Option<Notification>) {
+        let level = self.policy.level(policy::usage(value, self.max), self.reached);
 
-        if percentage_of_max >= 1.0 {
-            println!("You're over your quota.")
-        } else if percentage_of_max >= 0.9 {
-            self.messenger.send("Urgent warning: You've used up over 90% of your quota")
-        } else if percentage_of_max >= 0.75 {
-            self.messenger.send("Warning: You've used up over 75% of your quota.")
+        let due = if level > self.reached {
+            let threshold = &self.policy.thresholds()[level - 1];
+            let message = threshold.message(value, self.max);
+            Some(self.notification(NotificationKind::Reached, threshold.severity, threshold.fraction, value, message))
+        } else if level < self.reached {
+            let threshold = &self.policy.thresholds()[level];
+            let message = self.policy.recovery_message(threshold, value, self.max);
+            Some(self.notification(NotificationKind::Recovered, Severity::Info, threshold.fraction, value, message))
+        } else {
+            None
+        };
+        (level, due)
+    }
+
+    fn notification(&self, kind: NotificationKind, severity: Severity, threshold: f64, value: usize,
+                    message: String) -> Notification {
+        Notification {
+            tracker_id: self.id.clone(),
+            kind,
+            severity,
+            threshold,
+            value,
+            max: self.max,
+            percentage: policy::usage(value, self.max) * 100.0,
+            timestamp: SystemTime::now(),
+            message,
         }
     }
 }
@@ -35,32 +186,222 @@ impl<'a, T> LimitTracker<'a, T> where T: Messenger {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::cell::RefCell;
+    use crate::mock::MockMessenger;
+
+    #[test]
+    fn it_sends_an_over_75_percent_warning_message() {
+        let mock_messenger = MockMessenger::new();
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 100);
 
-    struct MockMessenger {
-        sent_messages: RefCell<Vec<String>>,
+        limit_tracker.set_value(80).unwrap();
+        assert_eq!(mock_messenger.messages().len(), 1);
     }
 
-    impl MockMessenger {
-        fn new() -> MockMessenger {
-            MockMessenger {
-                sent_messages: RefCell::new(vec![]),
-            }
-        }
+    fn sent(mock_messenger: &MockMessenger) -> Vec<String> {
+        mock_messenger.take().into_iter().map(|n| n.message).collect()
     }
 
-    impl Messenger for MockMessenger {
-        fn send(&self, msg: &str) {
-            self.sent_messages.borrow_mut().push(String::from(msg));
+    #[test]
+    fn it_notifies_on_every_transition_up() {
+        let mock_messenger = MockMessenger::new();
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 100);
+
+        limit_tracker.set_value(50).unwrap();
+        assert!(sent(&mock_messenger).is_empty());
+
+        limit_tracker.set_value(75).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["Warning: You've used up over 75% of your quota."]);
+
+        limit_tracker.set_value(90).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["Urgent warning: You've used up over 90% of your quota"]);
+
+        limit_tracker.set_value(100).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["You're over your quota."]);
+
+        limit_tracker.set_value(150).unwrap();
+        assert!(sent(&mock_messenger).is_empty());
+    }
+
+    #[test]
+    fn it_notifies_on_every_transition_down() {
+        let mock_messenger = MockMessenger::new();
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 100);
+        limit_tracker.set_value(120).unwrap();
+        sent(&mock_messenger);
+
+        limit_tracker.set_value(95).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["Good news: you're back under 100% of your quota."]);
+
+        limit_tracker.set_value(80).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["Good news: you're back under 90% of your quota."]);
+
+        limit_tracker.set_value(10).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["Good news: you're back under 75% of your quota."]);
+
+        limit_tracker.set_value(0).unwrap();
+        assert!(sent(&mock_messenger).is_empty());
+    }
+
+    #[test]
+    fn it_notifies_when_jumping_over_quota_and_back() {
+        let mock_messenger = MockMessenger::new();
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 100);
+
+        limit_tracker.set_value(200).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["You're over your quota."]);
+
+        limit_tracker.set_value(20).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["Good news: you're back under 75% of your quota."]);
+    }
+
+    #[test]
+    fn it_uses_a_custom_recovery_message() {
+        let mock_messenger = MockMessenger::new();
+        let policy = QuotaPolicy::default().recovery("Down to {percent}%, under {threshold}%");
+        let mut limit_tracker = LimitTracker::with_policy(&mock_messenger, 100, policy);
+
+        limit_tracker.set_value(91).unwrap();
+        limit_tracker.set_value(60).unwrap();
+        assert_eq!(sent(&mock_messenger), vec![
+            "Urgent warning: You've used up over 90% of your quota",
+            "Down to 60%, under 75%",
+        ]);
+    }
+
+    #[test]
+    fn it_sends_structured_notifications() {
+        let mock_messenger = MockMessenger::new();
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 200).with_id("user-42");
+
+        let before = SystemTime::now();
+        limit_tracker.set_value(185).unwrap();
+        limit_tracker.set_value(20).unwrap();
+
+        let notifications = mock_messenger.notifications();
+        assert_eq!(notifications.len(), 2);
+
+        let warning = &notifications[0];
+        assert_eq!(warning.tracker_id, "user-42");
+        assert_eq!(warning.kind, NotificationKind::Reached);
+        assert_eq!(warning.severity, Severity::Urgent);
+        assert_eq!(warning.threshold, 0.9);
+        assert_eq!((warning.value, warning.max), (185, 200));
+        assert_eq!(warning.percentage, 92.5);
+        assert!(warning.timestamp >= before);
+        assert_eq!(warning.to_string(),
+                   "[urgent] user-42: Urgent warning: You've used up over 90% of your quota (185/200, 92%)");
+
+        let recovered = &notifications[1];
+        assert_eq!(recovered.kind, NotificationKind::Recovered);
+        assert_eq!(recovered.severity, Severity::Info);
+        assert_eq!(recovered.threshold, 0.75);
+    }
+
+    #[test]
+    fn it_reports_failed_notifications_and_tries_again() {
+        let mock_messenger = MockMessenger::new().failing(1);
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 100);
+
+        assert_eq!(limit_tracker.set_value(80).unwrap_err().to_string(), "mock messenger told to fail");
+
+        // the warning wasn't delivered, so it's still due
+        limit_tracker.set_value(81).unwrap();
+        assert_eq!(sent(&mock_messenger), vec!["Warning: You've used up over 75% of your quota."]);
+    }
+
+    #[test]
+    fn it_uses_custom_thresholds_and_templates() {
+        let mock_messenger = MockMessenger::new();
+        let policy = QuotaPolicy::new()
+            .threshold(0.5, Severity::Info, "Half way: {value}/{max}")
+            .threshold(0.8, Severity::Warning, "{percent}% used");
+        let mut limit_tracker = LimitTracker::with_policy(&mock_messenger, 200, policy);
+
+        limit_tracker.set_value(90).unwrap();
+        limit_tracker.set_value(120).unwrap();
+        limit_tracker.set_value(170).unwrap();
+
+        assert_eq!(mock_messenger.messages(), vec!["Half way: 120/200", "85% used"]);
+    }
+
+    #[test]
+    fn it_does_not_repeat_a_warning_while_bouncing_around_a_threshold() {
+        let mock_messenger = MockMessenger::new();
+        let policy = QuotaPolicy::default().hysteresis(0.05);
+        let mut limit_tracker = LimitTracker::with_policy(&mock_messenger, 100, policy);
+
+        for value in [76, 74, 77, 73, 78, 75] {
+            limit_tracker.set_value(value).unwrap();
         }
+        assert_eq!(sent(&mock_messenger).len(), 1);
+
+        // dropping out of the band re-arms the warning
+        limit_tracker.set_value(69).unwrap();
+        limit_tracker.set_value(76).unwrap();
+        assert_eq!(sent(&mock_messenger), vec![
+            "Good news: you're back under 75% of your quota.",
+            "Warning: You've used up over 75% of your quota.",
+        ]);
     }
 
     #[test]
-    fn it_sends_an_over_75_percent_warning_message() {
+    fn it_picks_up_where_a_stored_tracker_left_off() {
+        let mock_messenger = MockMessenger::new();
+        let store = stores::MemoryStore::new();
+
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 100).with_id("user-42").with_store(&store).unwrap();
+        limit_tracker.set_value(80).unwrap();
+        drop(limit_tracker);
+
+        // a restarted tracker knows the warning went out already
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 100).with_id("user-42").with_store(&store).unwrap();
+        assert_eq!(limit_tracker.value(), 80);
+        limit_tracker.set_value(85).unwrap();
+        limit_tracker.set_value(50).unwrap();
+        assert_eq!(sent(&mock_messenger), vec![
+            "Warning: You've used up over 75% of your quota.",
+            "Good news: you're back under 75% of your quota.",
+        ]);
+
+        let history = store.history(&HistoryQuery::new().tracker("user-42")).unwrap();
+        let kinds: Vec<NotificationKind> = history.iter().map(|n| n.kind).collect();
+        assert_eq!(kinds, vec![NotificationKind::Recovered, NotificationKind::Reached]);
+    }
+
+    #[test]
+    fn a_stored_tracker_keeps_its_max() {
+        let mock_messenger = MockMessenger::new();
+        let store = stores::MemoryStore::new();
+        LimitTracker::new(&mock_messenger, 100).with_id("user-42").with_store(&store).unwrap().set_value(80).unwrap();
+
+        let err = LimitTracker::new(&mock_messenger, 200).with_id("user-42").with_store(&store).err().unwrap();
+        assert_eq!(err.to_string(), "tracker `user-42` was stored with a max of 100, not 200");
+
+        store.remove("user-42").unwrap();
+        let limit_tracker = LimitTracker::new(&mock_messenger, 200).with_id("user-42").with_store(&store).unwrap();
+        assert_eq!(limit_tracker.value(), 0);
+    }
+
+    #[test]
+    fn a_zero_max_is_always_over_quota() {
+        let mock_messenger = MockMessenger::new();
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 0);
+
+        limit_tracker.set_value(0).unwrap();
+        limit_tracker.set_value(5).unwrap();
+        assert_eq!(mock_messenger.messages(), vec!["You're over your quota."]);
+
+        let notification = &mock_messenger.notifications()[0];
+        assert_eq!(notification.percentage, 100.0);
+        assert_eq!(notification.to_string(), "[critical] default: You're over your quota. (0/0, 100%)");
+    }
+
+    #[test]
+    fn it_sends_only_the_highest_new_threshold() {
         let mock_messenger = MockMessenger::new();
         let mut limit_tracker = LimitTracker::new(&mock_messenger, 100);
 
-        limit_tracker.set_value(80);
-        assert_eq!(mock_messenger.sent_messages.borrow().len(), 1);
+        limit_tracker.set_value(95).unwrap();
+        assert_eq!(mock_messenger.messages(), vec!["Urgent warning: You've used up over 90% of your quota"]);
     }
 }
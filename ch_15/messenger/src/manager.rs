@@ -0,0 +1,542 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Messenger, QuotaPolicy, SendError, StateStore, StoreError, TrackerState, Watcher};
+
+pub const MINUTE: Duration = Duration::from_secs(60);
+pub const HOUR: Duration = Duration::from_secs(60 * 60);
+pub const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// How long an increment counts against a quota. Windows go by the wall
+// clock, so they carry on across restarts when the manager has a store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Window {
+    // usage is never forgotten
+    Unbounded,
+    // usage starts again from 0 every `Duration`, counted from the key's
+    // first increment
+    Fixed(Duration),
+    // each increment counts for `Duration` after it was made, less up to
+    // one bucket: increments are kept in `SLIDING_BUCKETS` buckets a window,
+    // and a bucket is forgotten once the window has passed since it opened
+    Sliding(Duration),
+}
+
+// How finely a sliding window keeps its usage. However many increments a
+// key gets, it never keeps more than one more bucket than this.
+const SLIDING_BUCKETS: u32 = 60;
+
+// One key's usage in the current window.
+struct Quota {
+    watcher: Watcher,
+    // whether `watcher.max` was set for this key alone, rather than being
+    // the manager's default
+    own_limit: bool,
+    // the usage of an unbounded or fixed window
+    used: usize,
+    // when the current fixed window opened
+    opened: Option<SystemTime>,
+    // a sliding window's usage, by when each bucket opened, oldest first
+    recent: VecDeque<(SystemTime, usize)>,
+    // the sum of `recent`, kept wide enough that it never saturates, so
+    // taking an expired bucket back off always leaves the right total
+    recent_total: u128,
+}
+
+impl Quota {
+    fn new(key: &str, max: usize, policy: &QuotaPolicy) -> Quota {
+        Quota {
+            watcher: Watcher::new(key, max, policy.clone()),
+            own_limit: false,
+            used: 0,
+            opened: None,
+            recent: VecDeque::new(),
+            recent_total: 0,
+        }
+    }
+
+    // Stored sliding usage goes back into buckets, however finely it was
+    // kept, and its total is summed afresh rather than taken from `value`. A
+    // key without a limit of its own gets today's default.
+    fn restore(key: &str, state: TrackerState, window: Window, max: usize, policy: &QuotaPolicy) -> Quota {
+        let mut quota = Quota::new(key, state.max.unwrap_or(max), policy);
+        quota.own_limit = state.max.is_some();
+        quota.watcher.reached = state.reached;
+        quota.used = state.value;
+        quota.opened = state.window_opened;
+        if let Window::Sliding(length) = window {
+            for (at, amount) in state.recent {
+                quota.add_recent(length, at, amount);
+            }
+        }
+        quota
+    }
+
+    fn state(&self, window: Window) -> TrackerState {
+        TrackerState {
+            value: self.used(window),
+            max: self.own_limit.then_some(self.watcher.max),
+            reached: self.watcher.reached,
+            window_opened: self.opened,
+            recent: self.recent.iter().copied().collect(),
+        }
+    }
+
+    // the usage as of the last `expire`
+    fn used(&self, window: Window) -> usize {
+        match window {
+            Window::Sliding(_) => usize::try_from(self.recent_total).unwrap_or(usize::MAX),
+            _ => self.used,
+        }
+    }
+
+    fn used_at(&self, window: Window, now: SystemTime) -> usize {
+        match window {
+            Window::Unbounded => self.used,
+            Window::Fixed(length) => match self.opened {
+                Some(opened) if since(opened, now) < length => self.used,
+                _ => 0,
+            },
+            Window::Sliding(length) => self
+                .recent
+                .iter()
+                .filter(|(at, _)| since(*at, now) < length)
+                .fold(0, |used, (_, amount)| used.saturating_add(*amount)),
+        }
+    }
+
+    // forgets whatever has fallen out of the window by `now`
+    fn expire(&mut self, window: Window, now: SystemTime) {
+        match window {
+            Window::Unbounded => {}
+            Window::Fixed(length) => {
+                if let Some(opened) = self.opened {
+                    let elapsed = since(opened, now);
+                    if elapsed >= length {
+                        // windows line up with the first one, however long the key sat idle
+                        let into = elapsed.as_nanos() % length.as_nanos();
+                        self.opened = Some(now - Duration::from_nanos(into as u64));
+                        self.used = 0;
+                    }
+                }
+            }
+            Window::Sliding(length) => {
+                while let Some(&(opened, amount)) = self.recent.front() {
+                    if since(opened, now) < length {
+                        break;
+                    }
+                    self.recent.pop_front();
+                    self.recent_total -= amount as u128;
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, window: Window, amount: usize, now: SystemTime) {
+        match window {
+            Window::Unbounded => self.used = self.used.saturating_add(amount),
+            Window::Fixed(_) => {
+                self.opened.get_or_insert(now);
+                self.used = self.used.saturating_add(amount);
+            }
+            Window::Sliding(length) => self.add_recent(length, now, amount),
+        }
+    }
+
+    // Adds to the bucket `at` falls in. Anything from before the newest
+    // bucket, as when the clock went back, goes into the newest one.
+    fn add_recent(&mut self, length: Duration, at: SystemTime, amount: usize) {
+        let opened = bucket(length, at);
+        match self.recent.back_mut() {
+            Some((newest, used)) if opened <= *newest => {
+                let before = *used;
+                *used = used.saturating_add(amount);
+                self.recent_total += (*used - before) as u128;
+            }
+            _ => {
+                self.recent.push_back((opened, amount));
+                self.recent_total += amount as u128;
+            }
+        }
+    }
+}
+
+// when the sliding window bucket that `at` falls in opened; buckets line up
+// with the Unix epoch
+fn bucket(length: Duration, at: SystemTime) -> SystemTime {
+    let width = (length / SLIDING_BUCKETS).as_nanos().max(1);
+    let elapsed = since(UNIX_EPOCH, at).as_nanos();
+    UNIX_EPOCH + Duration::from_nanos((elapsed - elapsed % width) as u64)
+}
+
+// a clock that went backwards counts as no time passing
+fn since(earlier: SystemTime, now: SystemTime) -> Duration {
+    now.duration_since(earlier).unwrap_or_default()
+}
+
+// Tracks usage for many keys at once, e.g. one per user or API key, and warns
+// through its messenger when a key crosses a threshold of the policy. Every
+// key shares the window and policy; the limit can be set per key.
+pub struct QuotaManager<M: Messenger> {
+    messenger: M,
+    max: usize,
+    window: Window,
+    policy: QuotaPolicy,
+    quotas: HashMap<String, Quota>,
+    store: Option<Box<dyn StateStore>>,
+}
+
+impl<M> QuotaManager<M> where M: Messenger {
+    // `max` is the limit for keys that don't have one of their own; panics
+    // if a window is zero long
+    pub fn new(messenger: M, max: usize, window: Window) -> QuotaManager<M> {
+        QuotaManager::with_policy(messenger, max, window, QuotaPolicy::default())
+    }
+
+    pub fn with_policy(messenger: M, max: usize, window: Window, policy: QuotaPolicy) -> QuotaManager<M> {
+        if let Window::Fixed(length) | Window::Sliding(length) = window {
+            assert!(!length.is_zero(), "window must not be zero long");
+        }
+        QuotaManager {
+            messenger,
+            max,
+            window,
+            policy,
+            quotas: HashMap::new(),
+            store: None,
+        }
+    }
+
+    // Keeps every key's usage, limit and warnings in `store`, starting from
+    // whatever is in it already. Only limits given with `set_limit` are
+    // stored; the other keys follow this manager's `max`, even if an earlier
+    // manager had another. Delivered notifications go into the store's
+    // history.
+    pub fn with_store<S: StateStore + 'static>(mut self, store: S) -> Result<QuotaManager<M>, StoreError> {
+        for (key, state) in store.load_all()? {
+            let quota = Quota::restore(&key, state, self.window, self.max, &self.policy);
+            self.quotas.insert(key, quota);
+        }
+        self.store = Some(Box::new(store));
+        Ok(self)
+    }
+
+    pub fn store(&self) -> Option<&dyn StateStore> {
+        self.store.as_deref()
+    }
+
+    pub fn messenger(&self) -> &M {
+        &self.messenger
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    pub fn limit(&self, key: &str) -> usize {
+        self.quotas.get(key).map_or(self.max, |quota| quota.watcher.max)
+    }
+
+    // gives `key` a limit of its own; takes effect from its next increment
+    pub fn set_limit(&mut self, key: &str, max: usize) -> Result<(), StoreError> {
+        let quota = self.quota(key);
+        quota.watcher.max = max;
+        quota.own_limit = true;
+        self.save(key)
+    }
+
+    // Adds `amount` to the key's usage, first forgetting anything that has
+    // fallen out of the window. The usage counts even when the notification
+    // fails; as with `LimitTracker::set_value` the next increment tries again.
+    pub fn increment(&mut self, key: &str, amount: usize) -> Result<(), SendError> {
+        self.increment_at(key, amount, SystemTime::now())
+    }
+
+    // `increment` as if it were `now`
+    pub fn increment_at(&mut self, key: &str, amount: usize, now: SystemTime) -> Result<(), SendError> {
+        let window = self.window;
+        let quota = self.quotas.entry(String::from(key)).or_insert_with(|| Quota::new(key, self.max, &self.policy));
+        quota.expire(window, now);
+        quota.add(window, amount, now);
+
+        let used = quota.used(window);
+        let sent = quota.watcher.update(&self.messenger, used);
+
+        self.save(key)?;
+        if let (Some(store), Ok(Some(notification))) = (&self.store, &sent) {
+            store.record(notification)?;
+        }
+        sent.map(|_| ())
+    }
+
+    pub fn usage(&self, key: &str) -> usize {
+        self.usage_at(key, SystemTime::now())
+    }
+
+    pub fn usage_at(&self, key: &str, now: SystemTime) -> usize {
+        self.quotas.get(key).map_or(0, |quota| quota.used_at(self.window, now))
+    }
+
+    pub fn remaining(&self, key: &str) -> usize {
+        self.limit(key).saturating_sub(self.usage(key))
+    }
+
+    // stops tracking `key`; returns whether it was tracked
+    pub fn remove(&mut self, key: &str) -> Result<bool, StoreError> {
+        if let Some(store) = &self.store {
+            store.remove(key)?;
+        }
+        Ok(self.quotas.remove(key).is_some())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.quotas.keys().map(String::as_str)
+    }
+
+    fn quota(&mut self, key: &str) -> &mut Quota {
+        self.quotas.entry(String::from(key)).or_insert_with(|| Quota::new(key, self.max, &self.policy))
+    }
+
+    fn save(&self, key: &str) -> Result<(), StoreError> {
+        match (&self.store, self.quotas.get(key)) {
+            (Some(store), Some(quota)) => store.save(key, &quota.state(self.window)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockMessenger;
+    use crate::stores::MemoryStore;
+    use crate::{HistoryQuery, Severity};
+    use std::sync::Arc;
+
+    // the (tracker, message) pairs delivered so far, forgetting them
+    fn take(mock: &MockMessenger) -> Vec<(String, String)> {
+        mock.take().into_iter().map(|n| (n.tracker_id, n.message)).collect()
+    }
+
+    fn sent(tracker_id: &str, message: &str) -> (String, String) {
+        (String::from(tracker_id), String::from(message))
+    }
+
+    #[test]
+    fn keys_are_tracked_separately() {
+        let mock = MockMessenger::new();
+        let mut manager = QuotaManager::new(&mock, 100, Window::Unbounded);
+
+        manager.increment("alice", 50).unwrap();
+        manager.increment("bob", 60).unwrap();
+        manager.increment("alice", 30).unwrap();
+
+        assert_eq!(manager.usage("alice"), 80);
+        assert_eq!(manager.usage("bob"), 60);
+        assert_eq!(manager.usage("carol"), 0);
+        assert_eq!(take(&mock), vec![sent("alice", "Warning: You've used up over 75% of your quota.")]);
+    }
+
+    #[test]
+    fn keys_can_have_their_own_limit() {
+        let mock = MockMessenger::new();
+        let mut manager = QuotaManager::new(&mock, 100, Window::Unbounded);
+        manager.set_limit("enterprise", 1000).unwrap();
+
+        manager.increment("enterprise", 100).unwrap();
+        manager.increment("free", 100).unwrap();
+
+        assert_eq!(manager.limit("enterprise"), 1000);
+        assert_eq!(manager.remaining("enterprise"), 900);
+        assert_eq!(manager.remaining("free"), 0);
+        assert_eq!(take(&mock), vec![sent("free", "You're over your quota.")]);
+    }
+
+    #[test]
+    fn fixed_window_resets() {
+        let mock = MockMessenger::new();
+        let mut manager = QuotaManager::new(&mock, 10, Window::Fixed(MINUTE));
+        let start = SystemTime::UNIX_EPOCH + DAY;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        manager.increment_at("key", 8, at(0)).unwrap();
+        manager.increment_at("key", 1, at(59)).unwrap();
+        assert_eq!(manager.usage_at("key", at(59)), 9);
+        assert_eq!(manager.usage_at("key", at(60)), 0);
+
+        manager.increment_at("key", 1, at(61)).unwrap();
+        assert_eq!(take(&mock), vec![
+            sent("key", "Warning: You've used up over 75% of your quota."),
+            sent("key", "Urgent warning: You've used up over 90% of your quota"),
+            sent("key", "Good news: you're back under 75% of your quota."),
+        ]);
+    }
+
+    #[test]
+    fn fixed_windows_line_up_after_idling() {
+        let mut manager = QuotaManager::new(MockMessenger::new(), 10, Window::Fixed(MINUTE));
+        let start = SystemTime::UNIX_EPOCH + DAY;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        manager.increment_at("key", 1, at(0)).unwrap();
+        // the window that opened at 120s is still open at 179s
+        manager.increment_at("key", 2, at(150)).unwrap();
+        assert_eq!(manager.usage_at("key", at(179)), 2);
+        assert_eq!(manager.usage_at("key", at(180)), 0);
+    }
+
+    #[test]
+    fn sliding_window_forgets_old_increments() {
+        let mock = MockMessenger::new();
+        let mut manager = QuotaManager::new(&mock, 10, Window::Sliding(MINUTE));
+        let start = SystemTime::UNIX_EPOCH + DAY;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        manager.increment_at("key", 5, at(0)).unwrap();
+        manager.increment_at("key", 5, at(30)).unwrap();
+        assert_eq!(manager.usage_at("key", at(59)), 10);
+        assert_eq!(manager.usage_at("key", at(60)), 5);
+        assert_eq!(manager.usage_at("key", at(90)), 0);
+
+        manager.increment_at("key", 5, at(70)).unwrap();
+        assert_eq!(manager.usage_at("key", at(70)), 10);
+
+        manager.increment_at("key", 1, at(95)).unwrap();
+        assert_eq!(manager.usage_at("key", at(95)), 6);
+        assert_eq!(take(&mock), vec![
+            sent("key", "You're over your quota."),
+            sent("key", "Good news: you're back under 75% of your quota."),
+        ]);
+    }
+
+    #[test]
+    fn sliding_window_saturates_and_still_expires() {
+        let mut manager = QuotaManager::new(MockMessenger::new(), 10, Window::Sliding(MINUTE));
+        let start = SystemTime::UNIX_EPOCH + DAY;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        manager.increment_at("key", usize::MAX, at(0)).unwrap();
+        manager.increment_at("key", 5, at(30)).unwrap();
+        assert_eq!(manager.usage_at("key", at(30)), usize::MAX);
+
+        manager.increment_at("key", 1, at(60)).unwrap();
+        assert_eq!(manager.usage_at("key", at(60)), 6);
+    }
+
+    #[test]
+    fn sliding_window_keeps_its_usage_in_buckets() {
+        let store = Arc::new(MemoryStore::new());
+        let mut manager = QuotaManager::new(MockMessenger::new(), usize::MAX, Window::Sliding(DAY))
+            .with_store(store.clone())
+            .unwrap();
+        let start = SystemTime::UNIX_EPOCH + DAY;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // one a second for over two days
+        for secs in 0..200_000 {
+            manager.increment_at("key", 1, at(secs)).unwrap();
+        }
+
+        assert!(manager.quotas["key"].recent.len() <= SLIDING_BUCKETS as usize + 1);
+        assert!(store.load("key").unwrap().unwrap().recent.len() <= SLIDING_BUCKETS as usize + 1);
+        // buckets are 24 minutes wide, and the oldest still counted opened
+        // at 113,760s, the first bucket start less than a day before the end
+        assert_eq!(manager.usage_at("key", at(199_999)), 199_999 - 113_760 + 1);
+    }
+
+    #[test]
+    fn sliding_usage_comes_from_the_stored_increments() {
+        let store = Arc::new(MemoryStore::new());
+        let start = SystemTime::UNIX_EPOCH + DAY;
+        let at = |secs| start + Duration::from_secs(secs);
+        // a value that disagrees with the increments it should be the sum of
+        store.save("key", &TrackerState {
+            value: 1,
+            max: None,
+            reached: 0,
+            window_opened: None,
+            recent: vec![(at(0), 4), (at(30), 3)],
+        }).unwrap();
+
+        let mut manager = QuotaManager::new(MockMessenger::new(), 10, Window::Sliding(MINUTE))
+            .with_store(store.clone())
+            .unwrap();
+        manager.increment_at("key", 1, at(60)).unwrap();
+        assert_eq!(manager.usage_at("key", at(60)), 4);
+        assert_eq!(store.load("key").unwrap().unwrap().value, 4);
+    }
+
+    #[test]
+    fn removed_keys_start_over() {
+        let mut manager = QuotaManager::new(MockMessenger::new(), 10, Window::Unbounded);
+        manager.set_limit("key", 20).unwrap();
+        manager.increment("key", 5).unwrap();
+
+        assert!(manager.remove("key").unwrap());
+        assert!(!manager.remove("key").unwrap());
+        assert_eq!(manager.usage("key"), 0);
+        assert_eq!(manager.limit("key"), 10);
+        assert_eq!(manager.keys().count(), 0);
+    }
+
+    #[test]
+    fn stored_quotas_survive_a_restart() {
+        let mock = MockMessenger::new();
+        let store = Arc::new(MemoryStore::new());
+        let start = SystemTime::UNIX_EPOCH + DAY;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let mut manager = QuotaManager::new(&mock, 10, Window::Sliding(MINUTE)).with_store(store.clone()).unwrap();
+        manager.set_limit("big", 100).unwrap();
+        manager.increment_at("key", 8, at(0)).unwrap();
+        manager.increment_at("big", 8, at(0)).unwrap();
+        drop(manager);
+
+        let mut manager = QuotaManager::new(&mock, 10, Window::Sliding(MINUTE)).with_store(store.clone()).unwrap();
+        assert_eq!(manager.usage_at("key", at(30)), 8);
+        assert_eq!(manager.limit("big"), 100);
+
+        // the warning at 80% went out before the restart, and isn't repeated
+        manager.increment_at("key", 1, at(30)).unwrap();
+        manager.increment_at("key", 1, at(70)).unwrap();
+        assert_eq!(take(&mock), vec![
+            sent("key", "Warning: You've used up over 75% of your quota."),
+            sent("key", "Urgent warning: You've used up over 90% of your quota"),
+            sent("key", "Good news: you're back under 75% of your quota."),
+        ]);
+
+        let urgent = store.history(&HistoryQuery::new().tracker("key").min_severity(Severity::Warning)).unwrap();
+        let messages: Vec<&str> = urgent.iter().map(|n| n.message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "Urgent warning: You've used up over 90% of your quota",
+            "Warning: You've used up over 75% of your quota.",
+        ]);
+
+        manager.remove("key").unwrap();
+        assert_eq!(store.load("key").unwrap(), None);
+    }
+
+    #[test]
+    fn a_new_default_limit_reaches_stored_keys() {
+        let store = Arc::new(MemoryStore::new());
+        let mut manager = QuotaManager::new(MockMessenger::new(), 10, Window::Unbounded).with_store(store.clone()).unwrap();
+        manager.set_limit("big", 100).unwrap();
+        manager.increment("key", 5).unwrap();
+        manager.increment("big", 5).unwrap();
+        drop(manager);
+
+        // only the limit set for the key alone is kept across the change
+        let manager = QuotaManager::new(MockMessenger::new(), 20, Window::Unbounded).with_store(store.clone()).unwrap();
+        assert_eq!(manager.limit("key"), 20);
+        assert_eq!(manager.remaining("key"), 15);
+        assert_eq!(manager.limit("big"), 100);
+        assert_eq!(store.load("key").unwrap().unwrap().max, None);
+        assert_eq!(store.load("big").unwrap().unwrap().max, Some(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "zero long")]
+    fn rejects_an_empty_window() {
+        QuotaManager::new(MockMessenger::new(), 10, Window::Sliding(Duration::ZERO));
+    }
+}
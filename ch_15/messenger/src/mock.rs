@@ -0,0 +1,107 @@
+// A messenger to test trackers with, as both a `Messenger` and an
+// `AsyncMessenger`. Outside this crate's own tests it needs the `mock`
+// feature.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use crate::{AsyncMessenger, Messenger, Notification, SendError};
+
+// Remembers every notification it delivers, and counts every send, failed
+// or not. It can be told to fail its next few sends, and to make async sends
+// stay pending for a few polls, as a slow transport would.
+#[derive(Debug, Default)]
+pub struct MockMessenger {
+    sent: Mutex<Vec<Notification>>,
+    failures: AtomicU32,
+    calls: AtomicU32,
+    pending_polls: u32,
+}
+
+impl MockMessenger {
+    pub fn new() -> MockMessenger {
+        MockMessenger::default()
+    }
+
+    // fails the next `failures` sends
+    pub fn failing(self, failures: u32) -> MockMessenger {
+        self.failures.store(failures, Ordering::SeqCst);
+        self
+    }
+
+    // every async send returns `Pending` this many times before it's done
+    pub fn pending_polls(mut self, polls: u32) -> MockMessenger {
+        self.pending_polls = polls;
+        self
+    }
+
+    pub fn fail_next(&self, failures: u32) {
+        self.failures.store(failures, Ordering::SeqCst);
+    }
+
+    pub fn notifications(&self) -> Vec<Notification> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.sent.lock().unwrap().iter().map(|n| n.message.clone()).collect()
+    }
+
+    // the notifications delivered so far, forgetting them
+    pub fn take(&self) -> Vec<Notification> {
+        self.sent.lock().unwrap().drain(..).collect()
+    }
+
+    pub fn clear(&self) {
+        self.sent.lock().unwrap().clear();
+    }
+
+    // how many sends were attempted, including the failed ones
+    pub fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn deliver(&self, notification: &Notification) -> Result<(), SendError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let failing = self.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if failing.is_ok() {
+            return Err("mock messenger told to fail".into());
+        }
+        self.sent.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+}
+
+impl Messenger for MockMessenger {
+    fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        self.deliver(notification)
+    }
+}
+
+impl AsyncMessenger for MockMessenger {
+    async fn send(&self, notification: &Notification) -> Result<(), SendError> {
+        for _ in 0..self.pending_polls {
+            YieldNow(false).await;
+        }
+        self.deliver(notification)
+    }
+}
+
+// pending once, asking to be polled again straight away
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
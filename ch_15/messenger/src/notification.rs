@@ -0,0 +1,145 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::policy::Severity;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationKind {
+    // usage climbed past `threshold`
+    Reached,
+    // usage dropped back under `threshold`
+    Recovered,
+}
+
+// Everything a `Messenger` needs to decide where a notification goes and how
+// to show it. `message` is the policy's text for it; `Display` adds the
+// severity and tracker in front.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub tracker_id: String,
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    pub threshold: f64,
+    pub value: usize,
+    pub max: usize,
+    pub percentage: f64,
+    pub timestamp: SystemTime,
+    pub message: String,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Reached => "reached",
+            NotificationKind::Recovered => "recovered",
+        }
+    }
+}
+
+impl FromStr for NotificationKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<NotificationKind, String> {
+        match s {
+            "reached" => Ok(NotificationKind::Reached),
+            "recovered" => Ok(NotificationKind::Recovered),
+            _ => Err(format!("unknown notification kind `{}`", s)),
+        }
+    }
+}
+
+impl Notification {
+    // the JSON the file and webhook backends write; `timestamp` is in
+    // milliseconds since the Unix epoch
+    #[cfg(any(feature = "file", feature = "webhook", feature = "json"))]
+    pub fn to_json(&self) -> serde_json::Value {
+        let millis = self.timestamp.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+
+        serde_json::json!({
+            "tracker_id": self.tracker_id,
+            "kind": self.kind.as_str(),
+            "severity": self.severity.to_string(),
+            "threshold": self.threshold,
+            "value": self.value,
+            "max": self.max,
+            "percentage": self.percentage,
+            "timestamp": millis,
+            "message": self.message,
+        })
+    }
+
+    // reads back what `to_json` wrote
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &serde_json::Value) -> Result<Notification, String> {
+        let field = |name: &str| json.get(name).ok_or_else(|| format!("notification has no `{}`", name));
+        let text = |name: &str| field(name)?.as_str().ok_or_else(|| format!("`{}` is not a string", name));
+        let number = |name: &str| field(name)?.as_f64().ok_or_else(|| format!("`{}` is not a number", name));
+        let count = |name: &str| field(name)?.as_u64().ok_or_else(|| format!("`{}` is not a count", name));
+
+        Ok(Notification {
+            tracker_id: String::from(text("tracker_id")?),
+            kind: text("kind")?.parse()?,
+            severity: text("severity")?.parse()?,
+            threshold: number("threshold")?,
+            value: count("value")? as usize,
+            max: count("max")? as usize,
+            percentage: number("percentage")?,
+            timestamp: std::time::UNIX_EPOCH + std::time::Duration::from_millis(count("timestamp")?),
+            message: String::from(text("message")?),
+        })
+    }
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {} ({}/{}, {:.0}%)", self.severity, self.tracker_id, self.message, self.value,
+               self.max, self.percentage)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn notification() -> Notification {
+        Notification {
+            tracker_id: String::from("api-key-7"),
+            kind: NotificationKind::Reached,
+            severity: Severity::Urgent,
+            threshold: 0.9,
+            value: 93,
+            max: 100,
+            percentage: 93.0,
+            timestamp: SystemTime::UNIX_EPOCH,
+            message: String::from("Almost out"),
+        }
+    }
+
+    #[test]
+    fn formats_as_text() {
+        assert_eq!(notification().to_string(), "[urgent] api-key-7: Almost out (93/100, 93%)");
+    }
+
+    #[cfg(any(feature = "file", feature = "webhook", feature = "json"))]
+    #[test]
+    fn converts_to_json() {
+        let json = notification().to_json();
+
+        assert_eq!(json["tracker_id"], "api-key-7");
+        assert_eq!(json["kind"], "reached");
+        assert_eq!(json["threshold"], 0.9);
+        assert_eq!(json["max"], 100);
+        assert_eq!(json["timestamp"], 0);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn reads_back_its_json() {
+        assert_eq!(Notification::from_json(&notification().to_json()), Ok(notification()));
+
+        let mut json = notification().to_json();
+        json["severity"] = serde_json::json!("fatal");
+        assert_eq!(Notification::from_json(&json), Err(String::from("unknown severity `fatal`")));
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Urgent,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Urgent => "urgent",
+            Severity::Critical => "critical",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    // the lowercase names `Display` writes
+    fn from_str(s: &str) -> Result<Severity, String> {
+        match s {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "urgent" => Ok(Severity::Urgent),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(format!("unknown severity `{}`", s)),
+        }
+    }
+}
+
+// Fires when usage reaches `fraction` of the max. `template` may use
+// `{value}`, `{max}`, `{percent}` and `{threshold}`, which are filled in
+// when the message is sent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Threshold {
+    pub fraction: f64,
+    pub severity: Severity,
+    pub template: String,
+}
+
+const DEFAULT_RECOVERY: &str = "Good news: you're back under {threshold}% of your quota.";
+
+impl Threshold {
+    pub fn message(&self, value: usize, max: usize) -> String {
+        fill(&self.template, self.fraction, value, max)
+    }
+}
+
+// How much of `max` is used, as a fraction. A max of zero leaves no room at
+// all, so any value counts as the whole quota.
+pub(crate) fn usage(value: usize, max: usize) -> f64 {
+    if max == 0 { 1.0 } else { value as f64 / max as f64 }
+}
+
+fn fill(template: &str, fraction: f64, value: usize, max: usize) -> String {
+    let percent = usage(value, max) * 100.0;
+    template
+        .replace("{value}", &value.to_string())
+        .replace("{max}", &max.to_string())
+        .replace("{percent}", &format!("{:.0}", percent))
+        .replace("{threshold}", &format!("{:.0}", fraction * 100.0))
+}
+
+// The thresholds a `LimitTracker` warns about. A threshold warns once when
+// usage climbs past it and stays quiet until usage has dropped `hysteresis`
+// (a fraction of the max) below it, so a value bouncing around a threshold
+// doesn't repeat the same warning. Dropping back below a threshold sends the
+// `recovery` message, which takes the same placeholders.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuotaPolicy {
+    thresholds: Vec<Threshold>,
+    hysteresis: f64,
+    recovery: String,
+}
+
+impl QuotaPolicy {
+    pub fn new() -> QuotaPolicy {
+        QuotaPolicy {
+            thresholds: vec![],
+            hysteresis: 0.0,
+            recovery: String::from(DEFAULT_RECOVERY),
+        }
+    }
+
+    pub fn threshold(mut self, fraction: f64, severity: Severity, template: &str) -> QuotaPolicy {
+        assert!(fraction.is_finite() && fraction > 0.0, "threshold must be a positive fraction, got {}", fraction);
+
+        // kept sorted so the index of a threshold is also its rank
+        let at = self.thresholds.partition_point(|t| t.fraction <= fraction);
+        self.thresholds.insert(at, Threshold {
+            fraction,
+            severity,
+            template: String::from(template),
+        });
+        self
+    }
+
+    pub fn hysteresis(mut self, fraction: f64) -> QuotaPolicy {
+        assert!(fraction.is_finite() && fraction >= 0.0, "hysteresis must not be negative, got {}", fraction);
+        self.hysteresis = fraction;
+        self
+    }
+
+    pub fn recovery(mut self, template: &str) -> QuotaPolicy {
+        self.recovery = String::from(template);
+        self
+    }
+
+    pub fn recovery_message(&self, threshold: &Threshold, value: usize, max: usize) -> String {
+        fill(&self.recovery, threshold.fraction, value, max)
+    }
+
+    pub fn thresholds(&self) -> &[Threshold] {
+        &self.thresholds
+    }
+
+    // How many thresholds count as reached at `usage`, given that `reached`
+    // of them were reached before. Going up only needs the usage; coming
+    // back down also has to clear the hysteresis band.
+    pub(crate) fn level(&self, usage: f64, reached: usize) -> usize {
+        let mut level = self.thresholds.iter().take_while(|t| usage >= t.fraction).count();
+        while level < reached && usage > self.thresholds[level].fraction - self.hysteresis {
+            level += 1;
+        }
+        level
+    }
+}
+
+impl Default for QuotaPolicy {
+    // the warnings LimitTracker has always sent
+    fn default() -> QuotaPolicy {
+        QuotaPolicy::new()
+            .threshold(0.75, Severity::Warning, "Warning: You've used up over 75% of your quota.")
+            .threshold(0.9, Severity::Urgent, "Urgent warning: You've used up over 90% of your quota")
+            .threshold(1.0, Severity::Critical, "You're over your quota.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_are_kept_in_order() {
+        let policy = QuotaPolicy::new()
+            .threshold(0.9, Severity::Urgent, "b")
+            .threshold(0.5, Severity::Info, "a")
+            .threshold(1.2, Severity::Critical, "c");
+
+        let fractions: Vec<f64> = policy.thresholds().iter().map(|t| t.fraction).collect();
+        assert_eq!(fractions, vec![0.5, 0.9, 1.2]);
+    }
+
+    #[test]
+    fn fills_in_templates() {
+        let t = Threshold {
+            fraction: 0.8,
+            severity: Severity::Warning,
+            template: String::from("{value} of {max} used ({percent}%), warned at {threshold}%"),
+        };
+        assert_eq!(t.message(85, 100), "85 of 100 used (85%), warned at 80%");
+    }
+
+    #[test]
+    fn level_uses_hysteresis_on_the_way_down() {
+        let policy = QuotaPolicy::default().hysteresis(0.05);
+
+        assert_eq!(policy.level(0.5, 0), 0);
+        assert_eq!(policy.level(0.76, 0), 1);
+        assert_eq!(policy.level(0.74, 1), 1);
+        assert_eq!(policy.level(0.70, 1), 0);
+        assert_eq!(policy.level(0.95, 0), 2);
+        assert_eq!(policy.level(1.5, 2), 3);
+        assert_eq!(policy.level(0.97, 3), 3);
+        assert_eq!(policy.level(0.92, 3), 2);
+        assert_eq!(policy.level(0.80, 3), 1);
+    }
+
+    #[test]
+    fn severity_names_round_trip() {
+        for severity in [Severity::Info, Severity::Warning, Severity::Urgent, Severity::Critical] {
+            assert_eq!(severity.to_string().parse(), Ok(severity));
+        }
+        assert!("fatal".parse::<Severity>().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "positive fraction")]
+    fn rejects_zero_threshold() {
+        QuotaPolicy::new().threshold(0.0, Severity::Info, "never");
+    }
+}
@@ -0,0 +1,272 @@
+// Where trackers keep their state between runs, so a restart neither loses
+// usage nor repeats warnings that already went out. `MemoryStore` is always
+// available; the others sit behind the cargo feature of the same name.
+
+use std::cmp::Reverse;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::{Notification, Severity};
+
+#[cfg(feature = "json")]
+mod json;
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "json")]
+pub use json::JsonStore;
+pub use memory::MemoryStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+pub type StoreError = Box<dyn Error + Send + Sync>;
+
+// Everything a tracker needs to carry on where it left off. The window
+// fields are only used by a `QuotaManager` with that kind of window.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrackerState {
+    pub value: usize,
+    // the tracker's own limit; `None` for a quota manager key that uses the
+    // manager's default, so a change to the default reaches it
+    pub max: Option<usize>,
+    // how many of the policy's thresholds have already been warned about
+    pub reached: usize,
+    // when the current fixed window opened
+    pub window_opened: Option<SystemTime>,
+    // the increments still inside a sliding window, oldest first
+    pub recent: Vec<(SystemTime, usize)>,
+}
+
+pub trait StateStore {
+    fn load(&self, id: &str) -> Result<Option<TrackerState>, StoreError>;
+
+    fn load_all(&self) -> Result<Vec<(String, TrackerState)>, StoreError>;
+
+    fn save(&self, id: &str, state: &TrackerState) -> Result<(), StoreError>;
+
+    fn remove(&self, id: &str) -> Result<(), StoreError>;
+
+    // adds a delivered notification to the history
+    fn record(&self, notification: &Notification) -> Result<(), StoreError>;
+
+    // The recorded notifications that match `query`, newest first by
+    // timestamp; those with the same timestamp come most recently recorded
+    // first. Every store returns them in this order.
+    fn history(&self, query: &HistoryQuery) -> Result<Vec<Notification>, StoreError>;
+}
+
+impl<S: StateStore + ?Sized> StateStore for &S {
+    fn load(&self, id: &str) -> Result<Option<TrackerState>, StoreError> {
+        (**self).load(id)
+    }
+
+    fn load_all(&self) -> Result<Vec<(String, TrackerState)>, StoreError> {
+        (**self).load_all()
+    }
+
+    fn save(&self, id: &str, state: &TrackerState) -> Result<(), StoreError> {
+        (**self).save(id, state)
+    }
+
+    fn remove(&self, id: &str) -> Result<(), StoreError> {
+        (**self).remove(id)
+    }
+
+    fn record(&self, notification: &Notification) -> Result<(), StoreError> {
+        (**self).record(notification)
+    }
+
+    fn history(&self, query: &HistoryQuery) -> Result<Vec<Notification>, StoreError> {
+        (**self).history(query)
+    }
+}
+
+impl<S: StateStore + ?Sized> StateStore for Arc<S> {
+    fn load(&self, id: &str) -> Result<Option<TrackerState>, StoreError> {
+        (**self).load(id)
+    }
+
+    fn load_all(&self) -> Result<Vec<(String, TrackerState)>, StoreError> {
+        (**self).load_all()
+    }
+
+    fn save(&self, id: &str, state: &TrackerState) -> Result<(), StoreError> {
+        (**self).save(id, state)
+    }
+
+    fn remove(&self, id: &str) -> Result<(), StoreError> {
+        (**self).remove(id)
+    }
+
+    fn record(&self, notification: &Notification) -> Result<(), StoreError> {
+        (**self).record(notification)
+    }
+
+    fn history(&self, query: &HistoryQuery) -> Result<Vec<Notification>, StoreError> {
+        (**self).history(query)
+    }
+}
+
+// Which recorded notifications to return. Every condition that is set has to
+// match; the default matches everything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HistoryQuery {
+    pub tracker_id: Option<String>,
+    // no older than this
+    pub since: Option<SystemTime>,
+    // at least this severe
+    pub min_severity: Option<Severity>,
+    // at most this many, keeping the newest
+    pub limit: Option<usize>,
+}
+
+impl HistoryQuery {
+    pub fn new() -> HistoryQuery {
+        HistoryQuery::default()
+    }
+
+    pub fn tracker(mut self, id: &str) -> HistoryQuery {
+        self.tracker_id = Some(String::from(id));
+        self
+    }
+
+    pub fn since(mut self, time: SystemTime) -> HistoryQuery {
+        self.since = Some(time);
+        self
+    }
+
+    pub fn min_severity(mut self, severity: Severity) -> HistoryQuery {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> HistoryQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    // whether `notification` passes every condition apart from `limit`
+    pub fn matches(&self, notification: &Notification) -> bool {
+        self.tracker_id.as_ref().is_none_or(|id| *id == notification.tracker_id)
+            && self.since.is_none_or(|since| notification.timestamp >= since)
+            && self.min_severity.is_none_or(|severity| notification.severity >= severity)
+    }
+
+    // applies the query to notifications given in the order they were
+    // recorded, returning them in the order `StateStore::history` promises
+    pub(crate) fn select<'n, I>(&self, recorded: I) -> Vec<Notification>
+        where I: DoubleEndedIterator<Item = &'n Notification> {
+        let mut found: Vec<&Notification> = recorded.rev().filter(|n| self.matches(n)).collect();
+        // stable, so equal timestamps stay most recently recorded first
+        found.sort_by_key(|n| Reverse(n.timestamp));
+        found.into_iter().take(self.limit.unwrap_or(usize::MAX)).cloned().collect()
+    }
+}
+
+// times are stored as milliseconds since the Unix epoch
+#[cfg(any(feature = "json", feature = "sqlite"))]
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(any(feature = "json", feature = "sqlite"))]
+fn from_millis(millis: u64) -> SystemTime {
+    std::time::UNIX_EPOCH + std::time::Duration::from_millis(millis)
+}
+
+// The same checks for every store, so they all behave alike.
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::notification::tests::notification;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn sent(tracker_id: &str, severity: Severity, secs: u64) -> Notification {
+        Notification {
+            tracker_id: String::from(tracker_id),
+            severity,
+            timestamp: at(secs),
+            ..notification()
+        }
+    }
+
+    pub(crate) fn saves_and_loads(store: &dyn StateStore) {
+        assert_eq!(store.load("alice").unwrap(), None);
+
+        let state = TrackerState {
+            value: 80,
+            max: Some(100),
+            reached: 1,
+            window_opened: Some(at(1_000)),
+            recent: vec![(at(1_010), 30), (at(1_020), 50)],
+        };
+        store.save("alice", &state).unwrap();
+        store.save("bob", &TrackerState::default()).unwrap();
+        assert_eq!(store.load("alice").unwrap(), Some(state.clone()));
+
+        let changed = TrackerState { value: 5, recent: vec![], ..state };
+        store.save("alice", &changed).unwrap();
+        assert_eq!(store.load("alice").unwrap(), Some(changed.clone()));
+
+        let mut all = store.load_all().unwrap();
+        all.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(all, vec![(String::from("alice"), changed), (String::from("bob"), TrackerState::default())]);
+
+        store.remove("alice").unwrap();
+        assert_eq!(store.load("alice").unwrap(), None);
+    }
+
+    pub(crate) fn queries_history(store: &dyn StateStore) {
+        store.record(&sent("alice", Severity::Warning, 10)).unwrap();
+        store.record(&sent("bob", Severity::Critical, 20)).unwrap();
+        store.record(&sent("alice", Severity::Urgent, 30)).unwrap();
+        store.record(&sent("alice", Severity::Info, 40)).unwrap();
+
+        let everything = store.history(&HistoryQuery::new()).unwrap();
+        let times: Vec<SystemTime> = everything.iter().map(|n| n.timestamp).collect();
+        assert_eq!(times, vec![at(40), at(30), at(20), at(10)]);
+        assert_eq!(everything[1], sent("alice", Severity::Urgent, 30));
+
+        let alice = store.history(&HistoryQuery::new().tracker("alice").min_severity(Severity::Warning)).unwrap();
+        assert_eq!(alice, vec![sent("alice", Severity::Urgent, 30), sent("alice", Severity::Warning, 10)]);
+
+        let recent = store.history(&HistoryQuery::new().since(at(20)).limit(2)).unwrap();
+        assert_eq!(recent, vec![sent("alice", Severity::Info, 40), sent("alice", Severity::Urgent, 30)]);
+    }
+    // recorded out of order, with two sharing a timestamp
+    fn orders_history(store: &dyn StateStore) {
+        store.record(&sent("alice", Severity::Warning, 20)).unwrap();
+        store.record(&sent("bob", Severity::Warning, 10)).unwrap();
+        store.record(&sent("carol", Severity::Warning, 30)).unwrap();
+        store.record(&sent("dave", Severity::Warning, 20)).unwrap();
+
+        let everything = store.history(&HistoryQuery::new()).unwrap();
+        let trackers: Vec<&str> = everything.iter().map(|n| n.tracker_id.as_str()).collect();
+        assert_eq!(trackers, vec!["carol", "dave", "alice", "bob"]);
+
+        let newest = store.history(&HistoryQuery::new().limit(2)).unwrap();
+        assert_eq!(newest, everything[..2]);
+    }
+
+    #[test]
+    fn every_store_orders_history_alike() {
+        orders_history(&MemoryStore::new());
+
+        #[cfg(feature = "json")]
+        {
+            let path = std::env::temp_dir().join(format!("json_store_order_{}.json", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            orders_history(&JsonStore::open(&path).unwrap());
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[cfg(feature = "sqlite")]
+        orders_history(&SqliteStore::open_in_memory().unwrap());
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use crate::stores::{from_millis, to_millis, HistoryQuery, StateStore, StoreError, TrackerState};
+use crate::Notification;
+
+const DEFAULT_HISTORY_LIMIT: usize = 1000;
+
+// Keeps everything in one JSON file, rewritten after every change. That's
+// fine for a handful of trackers; with many, use `SqliteStore`. A quota
+// manager saves on every increment, but its sliding windows are kept in a
+// bounded number of buckets, so the file doesn't grow with the traffic.
+// Only the newest `history_limit` notifications are kept.
+#[derive(Debug)]
+pub struct JsonStore {
+    path: PathBuf,
+    history_limit: usize,
+    contents: Mutex<Contents>,
+}
+
+#[derive(Debug, Default)]
+struct Contents {
+    trackers: BTreeMap<String, TrackerState>,
+    // oldest first
+    history: Vec<Notification>,
+}
+
+impl JsonStore {
+    // reads `path` if it exists; otherwise it's created on the first change
+    pub fn open(path: &Path) -> Result<JsonStore, StoreError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(text) => parse(&serde_json::from_str(&text)?)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Contents::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(JsonStore {
+            path: path.to_path_buf(),
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            contents: Mutex::new(contents),
+        })
+    }
+
+    pub fn with_history_limit(mut self, limit: usize) -> JsonStore {
+        self.history_limit = limit;
+        self
+    }
+
+    // makes a change and writes the file; if writing fails the change is
+    // still made in memory, and goes out with the next write that works
+    fn change<F: FnOnce(&mut Contents)>(&self, f: F) -> Result<(), StoreError> {
+        let mut contents = self.contents.lock().unwrap();
+        f(&mut contents);
+
+        let excess = contents.history.len().saturating_sub(self.history_limit);
+        contents.history.drain(..excess);
+
+        let text = serde_json::to_string_pretty(&unparse(&contents))?;
+        // write aside and rename, so a crash never leaves half a file behind
+        let tmp = self.path.with_extension("tmp");
+        fs::File::create(&tmp)?.write_all(text.as_bytes())?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl StateStore for JsonStore {
+    fn load(&self, id: &str) -> Result<Option<TrackerState>, StoreError> {
+        Ok(self.contents.lock().unwrap().trackers.get(id).cloned())
+    }
+
+    fn load_all(&self) -> Result<Vec<(String, TrackerState)>, StoreError> {
+        let contents = self.contents.lock().unwrap();
+        Ok(contents.trackers.iter().map(|(id, state)| (id.clone(), state.clone())).collect())
+    }
+
+    fn save(&self, id: &str, state: &TrackerState) -> Result<(), StoreError> {
+        self.change(|contents| {
+            contents.trackers.insert(String::from(id), state.clone());
+        })
+    }
+
+    fn remove(&self, id: &str) -> Result<(), StoreError> {
+        self.change(|contents| {
+            contents.trackers.remove(id);
+        })
+    }
+
+    fn record(&self, notification: &Notification) -> Result<(), StoreError> {
+        self.change(|contents| contents.history.push(notification.clone()))
+    }
+
+    fn history(&self, query: &HistoryQuery) -> Result<Vec<Notification>, StoreError> {
+        Ok(query.select(self.contents.lock().unwrap().history.iter()))
+    }
+}
+
+fn unparse(contents: &Contents) -> Value {
+    let trackers: serde_json::Map<String, Value> = contents
+        .trackers
+        .iter()
+        .map(|(id, state)| {
+            let recent: Vec<Value> = state.recent.iter().map(|(at, amount)| json!([to_millis(*at), amount])).collect();
+            (id.clone(), json!({
+                "value": state.value,
+                "max": state.max,
+                "reached": state.reached,
+                "window_opened": state.window_opened.map(to_millis),
+                "recent": recent,
+            }))
+        })
+        .collect();
+    let history: Vec<Value> = contents.history.iter().map(Notification::to_json).collect();
+
+    json!({ "trackers": trackers, "history": history })
+}
+
+fn parse(json: &Value) -> Result<Contents, String> {
+    let mut contents = Contents::default();
+
+    let trackers = json["trackers"].as_object().ok_or("store has no `trackers` object")?;
+    for (id, state) in trackers {
+        contents.trackers.insert(id.clone(), parse_state(state).map_err(|err| format!("tracker `{}`: {}", id, err))?);
+    }
+
+    let history = json["history"].as_array().ok_or("store has no `history` array")?;
+    for notification in history {
+        contents.history.push(Notification::from_json(notification)?);
+    }
+
+    Ok(contents)
+}
+
+fn parse_state(json: &Value) -> Result<TrackerState, String> {
+    let count = |value: &Value, name: &str| value.as_u64().ok_or_else(|| format!("`{}` is not a count", name));
+    let value = count(&json["value"], "value")? as usize;
+    let max = match &json["max"] {
+        Value::Null => None,
+        max => Some(count(max, "max")? as usize),
+    };
+    let reached = count(&json["reached"], "reached")? as usize;
+
+    let window_opened = match &json["window_opened"] {
+        Value::Null => None,
+        opened => Some(from_millis(count(opened, "window_opened")?)),
+    };
+
+    let mut recent = vec![];
+    for entry in json["recent"].as_array().ok_or("`recent` is not an array")? {
+        match entry.as_array().map(Vec::as_slice) {
+            Some([at, amount]) => recent.push((from_millis(count(at, "recent")?), count(amount, "recent")? as usize)),
+            _ => return Err(String::from("`recent` entries must be [time, amount] pairs")),
+        }
+    }
+
+    Ok(TrackerState {
+        value,
+        max,
+        reached,
+        window_opened,
+        recent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::tests::notification;
+    use crate::stores::tests::{queries_history, saves_and_loads};
+    use std::{env, process};
+
+    fn path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("json_store_{}_{}.json", name, process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn keeps_state() {
+        let path = path("state");
+        saves_and_loads(&JsonStore::open(&path).unwrap());
+
+        // a fresh store sees what the last one wrote
+        let reopened = JsonStore::open(&path).unwrap();
+        assert_eq!(reopened.load("bob").unwrap(), Some(TrackerState::default()));
+        assert_eq!(reopened.load("alice").unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keeps_history() {
+        let path = path("history");
+        queries_history(&JsonStore::open(&path).unwrap());
+
+        let reopened = JsonStore::open(&path).unwrap();
+        assert_eq!(reopened.history(&HistoryQuery::new()).unwrap().len(), 4);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn drops_the_oldest_history() {
+        let path = path("limit");
+        let store = JsonStore::open(&path).unwrap().with_history_limit(2);
+        for value in 1..=3 {
+            store.record(&Notification { value, ..notification() }).unwrap();
+        }
+
+        let values: Vec<usize> = store.history(&HistoryQuery::new()).unwrap().iter().map(|n| n.value).collect();
+        assert_eq!(values, vec![3, 2]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_corrupt_file() {
+        let path = path("corrupt");
+        fs::write(&path, r#"{ "trackers": { "alice": { "value": "lots" } }, "history": [] }"#).unwrap();
+
+        let err = JsonStore::open(&path).unwrap_err();
+        assert_eq!(err.to_string(), "tracker `alice`: `value` is not a count");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::stores::{HistoryQuery, StateStore, StoreError, TrackerState};
+use crate::Notification;
+
+// Keeps everything in memory. Nothing survives the process, but it lets
+// several trackers share state and history within one run, and it's handy
+// in tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    trackers: Mutex<HashMap<String, TrackerState>>,
+    history: Mutex<Vec<Notification>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn load(&self, id: &str) -> Result<Option<TrackerState>, StoreError> {
+        Ok(self.trackers.lock().unwrap().get(id).cloned())
+    }
+
+    fn load_all(&self) -> Result<Vec<(String, TrackerState)>, StoreError> {
+        let trackers = self.trackers.lock().unwrap();
+        Ok(trackers.iter().map(|(id, state)| (id.clone(), state.clone())).collect())
+    }
+
+    fn save(&self, id: &str, state: &TrackerState) -> Result<(), StoreError> {
+        self.trackers.lock().unwrap().insert(String::from(id), state.clone());
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), StoreError> {
+        self.trackers.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn record(&self, notification: &Notification) -> Result<(), StoreError> {
+        self.history.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+
+    fn history(&self, query: &HistoryQuery) -> Result<Vec<Notification>, StoreError> {
+        Ok(query.select(self.history.lock().unwrap().iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::tests::{queries_history, saves_and_loads};
+
+    #[test]
+    fn keeps_state() {
+        saves_and_loads(&MemoryStore::new());
+    }
+
+    #[test]
+    fn keeps_history() {
+        queries_history(&MemoryStore::new());
+    }
+}
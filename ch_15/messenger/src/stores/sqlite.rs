@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::stores::{from_millis, to_millis, HistoryQuery, StateStore, StoreError, TrackerState};
+use crate::Notification;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS trackers (
+        id TEXT PRIMARY KEY,
+        value INTEGER NOT NULL,
+        max INTEGER,
+        reached INTEGER NOT NULL,
+        window_opened INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS recent (
+        tracker_id TEXT NOT NULL REFERENCES trackers (id) ON DELETE CASCADE,
+        at INTEGER NOT NULL,
+        amount INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS recent_by_tracker ON recent (tracker_id);
+    CREATE TABLE IF NOT EXISTS history (
+        tracker_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        threshold REAL NOT NULL,
+        value INTEGER NOT NULL,
+        max INTEGER NOT NULL,
+        percentage REAL NOT NULL,
+        timestamp INTEGER NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS history_by_time ON history (timestamp);
+";
+
+// Keeps everything in a SQLite database, one row per tracker and one per
+// notification, so it copes with many trackers and a long history.
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    // creates the database and its tables if they aren't there yet
+    pub fn open(path: &Path) -> Result<SqliteStore, StoreError> {
+        SqliteStore::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<SqliteStore, StoreError> {
+        SqliteStore::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<SqliteStore, StoreError> {
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { connection })
+    }
+
+    fn recent(&self, id: &str) -> Result<Vec<(std::time::SystemTime, usize)>, StoreError> {
+        let mut statement = self.connection.prepare_cached("SELECT at, amount FROM recent WHERE tracker_id = ?1 ORDER BY at, rowid")?;
+        let rows = statement.query_map([id], |row| Ok((from_millis(row.get(0)?), row.get(1)?)))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn state(&self, row: &Row) -> rusqlite::Result<TrackerState> {
+        Ok(TrackerState {
+            value: row.get("value")?,
+            max: row.get("max")?,
+            reached: row.get("reached")?,
+            window_opened: row.get::<_, Option<u64>>("window_opened")?.map(from_millis),
+            recent: vec![],
+        })
+    }
+}
+
+impl StateStore for SqliteStore {
+    fn load(&self, id: &str) -> Result<Option<TrackerState>, StoreError> {
+        let state = self
+            .connection
+            .query_row("SELECT * FROM trackers WHERE id = ?1", [id], |row| self.state(row))
+            .optional()?;
+
+        match state {
+            Some(mut state) => {
+                state.recent = self.recent(id)?;
+                Ok(Some(state))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn load_all(&self) -> Result<Vec<(String, TrackerState)>, StoreError> {
+        let mut statement = self.connection.prepare("SELECT * FROM trackers ORDER BY id")?;
+        let rows = statement.query_map([], |row| Ok((row.get("id")?, self.state(row)?)))?;
+
+        let mut all = vec![];
+        for row in rows {
+            let (id, mut state): (String, TrackerState) = row?;
+            state.recent = self.recent(&id)?;
+            all.push((id, state));
+        }
+        Ok(all)
+    }
+
+    fn save(&self, id: &str, state: &TrackerState) -> Result<(), StoreError> {
+        let transaction = self.connection.unchecked_transaction()?;
+        transaction.execute(
+            "INSERT INTO trackers (id, value, max, reached, window_opened) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (id) DO UPDATE SET value = ?2, max = ?3, reached = ?4, window_opened = ?5",
+            params![id, state.value, state.max, state.reached, state.window_opened.map(to_millis)],
+        )?;
+
+        // Only the rows that changed are written. A quota manager saves after
+        // every increment, which usually grows the newest bucket and now and
+        // then expires the oldest, so that's a row or two rather than all.
+        let mut wanted: BTreeMap<u64, usize> = BTreeMap::new();
+        for (at, amount) in &state.recent {
+            let bucket = wanted.entry(to_millis(*at)).or_default();
+            *bucket = bucket.saturating_add(*amount);
+        }
+        let stored: Vec<(u64, usize, usize)> = {
+            let mut statement = transaction.prepare_cached(
+                "SELECT at, MAX(amount), COUNT(*) FROM recent WHERE tracker_id = ?1 GROUP BY at",
+            )?;
+            let rows = statement.query_map([id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        for (at, amount, rows) in stored {
+            if rows == 1 && wanted.get(&at) == Some(&amount) {
+                wanted.remove(&at);
+            } else {
+                transaction.execute("DELETE FROM recent WHERE tracker_id = ?1 AND at = ?2", params![id, at])?;
+            }
+        }
+        for (at, amount) in wanted {
+            transaction.execute("INSERT INTO recent (tracker_id, at, amount) VALUES (?1, ?2, ?3)",
+                                params![id, at, amount])?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), StoreError> {
+        self.connection.execute("DELETE FROM trackers WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    fn record(&self, n: &Notification) -> Result<(), StoreError> {
+        self.connection.execute(
+            "INSERT INTO history (tracker_id, kind, severity, threshold, value, max, percentage, timestamp, message)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![n.tracker_id, n.kind.as_str(), n.severity.to_string(), n.threshold, n.value, n.max,
+                    n.percentage, to_millis(n.timestamp), n.message],
+        )?;
+        Ok(())
+    }
+
+    fn history(&self, query: &HistoryQuery) -> Result<Vec<Notification>, StoreError> {
+        // severity is checked while reading, since it's stored by name
+        let mut statement = self.connection.prepare(
+            "SELECT * FROM history
+             WHERE (?1 IS NULL OR tracker_id = ?1) AND (?2 IS NULL OR timestamp >= ?2)
+             ORDER BY timestamp DESC, rowid DESC",
+        )?;
+        let mut rows = statement.query(params![query.tracker_id, query.since.map(to_millis)])?;
+
+        let mut found = vec![];
+        while found.len() < query.limit.unwrap_or(usize::MAX) {
+            let Some(row) = rows.next()? else { break };
+            let notification = Notification {
+                tracker_id: row.get("tracker_id")?,
+                kind: row.get::<_, String>("kind")?.parse()?,
+                severity: row.get::<_, String>("severity")?.parse()?,
+                threshold: row.get("threshold")?,
+                value: row.get("value")?,
+                max: row.get("max")?,
+                percentage: row.get("percentage")?,
+                timestamp: from_millis(row.get("timestamp")?),
+                message: row.get("message")?,
+            };
+            if query.matches(&notification) {
+                found.push(notification);
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::tests::{queries_history, saves_and_loads};
+    use std::{env, fs, process};
+
+    #[test]
+    fn keeps_state() {
+        saves_and_loads(&SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn keeps_history() {
+        queries_history(&SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn writes_only_the_changed_recent_rows() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let at = |secs: u64| from_millis(secs * 1000);
+        let mut state = TrackerState { recent: vec![(at(1), 1), (at(2), 2)], ..TrackerState::default() };
+        store.save("alice", &state).unwrap();
+        let rowid = |at: u64| -> i64 {
+            store.connection.query_row("SELECT rowid FROM recent WHERE at = ?1", [at * 1000], |row| row.get(0)).unwrap()
+        };
+        let first = rowid(1);
+
+        state.recent = vec![(at(1), 1), (at(2), 3), (at(3), 1)];
+        store.save("alice", &state).unwrap();
+        assert_eq!(rowid(1), first);
+        assert_eq!(store.load("alice").unwrap().unwrap().recent, state.recent);
+
+        state.recent.remove(0);
+        store.save("alice", &state).unwrap();
+        assert_eq!(store.load("alice").unwrap().unwrap().recent, state.recent);
+    }
+
+    #[test]
+    fn survives_reopening() {
+        let path = env::temp_dir().join(format!("sqlite_store_{}.db", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let state = TrackerState { value: 3, max: Some(10), ..TrackerState::default() };
+        SqliteStore::open(&path).unwrap().save("alice", &state).unwrap();
+
+        assert_eq!(SqliteStore::open(&path).unwrap().load("alice").unwrap(), Some(state));
+        fs::remove_file(&path).unwrap();
+    }
+}
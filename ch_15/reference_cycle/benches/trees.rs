@@ -0,0 +1,62 @@
+use std::hint::black_box;
+use std::rc::Rc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use reference_cycle::{ArenaTree, Node, NodeId, Tree};
+
+const NODES: u32 = 1_000_000;
+
+// Node `i` hangs under node `(i - 1) / 10`: a wide, shallow tree about six
+// levels deep, so `add_child`'s walk up for cycles stays cheap on both sides.
+fn rc_tree() -> Tree<u32> {
+    let nodes: Vec<Tree<u32>> = (0..NODES).map(Node::new).collect();
+    for i in 1..NODES as usize {
+        nodes[(i - 1) / 10].add_child(Rc::clone(&nodes[i]));
+    }
+    Rc::clone(&nodes[0])
+}
+
+fn arena_tree() -> (ArenaTree<u32>, NodeId) {
+    let mut tree = ArenaTree::with_capacity(NODES as usize);
+    let ids: Vec<NodeId> = (0..NODES).map(|i| tree.insert(i)).collect();
+    for i in 1..NODES as usize {
+        tree.add_child(ids[(i - 1) / 10], ids[i]);
+    }
+    (tree, ids[0])
+}
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build and drop, 1M nodes");
+    group.sample_size(10);
+
+    group.bench_function("rc", |b| b.iter(|| black_box(rc_tree())));
+    group.bench_function("arena", |b| b.iter(|| black_box(arena_tree())));
+
+    group.finish();
+}
+
+fn traverse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("traverse, 1M nodes");
+    group.sample_size(10);
+
+    let root = rc_tree();
+    let (tree, arena_root) = arena_tree();
+
+    group.bench_function("rc preorder", |b| {
+        b.iter(|| root.preorder().map(|n| n.value as u64).sum::<u64>())
+    });
+    group.bench_function("arena preorder", |b| {
+        b.iter(|| tree.preorder(arena_root).map(|id| tree[id] as u64).sum::<u64>())
+    });
+    group.bench_function("rc breadth first", |b| {
+        b.iter(|| root.breadth_first().map(|n| n.value as u64).sum::<u64>())
+    });
+    group.bench_function("arena breadth first", |b| {
+        b.iter(|| tree.breadth_first(arena_root).map(|id| tree[id] as u64).sum::<u64>())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, build, traverse);
+criterion_main!(benches);
@@ -0,0 +1,500 @@
+use std::collections::VecDeque;
+use std::ops::{Index, IndexMut};
+
+// A handle to a node in an `ArenaTree`. Removing a node retires its id: the
+// slot's generation goes up, so an old id stops matching instead of quietly
+// naming whatever is stored in the slot next.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot<T> {
+    generation: u32,
+    node: Option<ArenaNode<T>>,
+}
+
+// Links between nodes are bare slot indices: they only ever point at live
+// nodes, so they need no generation to check. Children are kept as a list
+// threaded through their sibling links, so adding and detaching a child
+// never searches or allocates.
+struct ArenaNode<T> {
+    value: T,
+    parent: Option<u32>,
+    first_child: Option<u32>,
+    last_child: Option<u32>,
+    prev_sibling: Option<u32>,
+    next_sibling: Option<u32>,
+}
+
+// The same tree as `Node`, with every node in one `Vec` instead of an `Rc`
+// each, and no `RefCell`s to borrow on the way through. The arena owns all its
+// nodes, so nothing goes away when it's detached; `remove` frees a subtree.
+//
+// Queries about an id that no longer names a node act as if it were a lone
+// node with nothing in it: `get` and `parent` give `None`, and traversals are
+// empty. Changes to the shape of the tree, and indexing, panic on one.
+pub struct ArenaTree<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T> ArenaTree<T> {
+    pub fn new() -> ArenaTree<T> {
+        ArenaTree::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> ArenaTree<T> {
+        ArenaTree {
+            slots: Vec::with_capacity(capacity),
+            free: vec![],
+            len: 0,
+        }
+    }
+
+    // adds a node with no parent and no children
+    pub fn insert(&mut self, value: T) -> NodeId {
+        let node = ArenaNode {
+            value,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            prev_sibling: None,
+            next_sibling: None,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index as usize].node = Some(node);
+                index
+            }
+            None => {
+                let index = u32::try_from(self.slots.len()).expect("an ArenaTree holds at most u32::MAX nodes");
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                index
+            }
+        };
+        self.len += 1;
+        self.id(index)
+    }
+
+    // Takes `id` and everything under it out of the arena, and gives back its
+    // value; the values under it are dropped. `None` if `id` was already gone.
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.node(id)?;
+        self.detach(id);
+
+        let doomed: Vec<NodeId> = self.preorder(id).collect();
+        let mut value = None;
+        for gone in doomed {
+            let slot = &mut self.slots[gone.index as usize];
+            slot.generation = slot.generation.wrapping_add(1);
+            let node = slot.node.take().unwrap();
+            if gone == id {
+                value = Some(node.value);
+            }
+            self.free.push(gone.index);
+            self.len -= 1;
+        }
+        value
+    }
+
+    // how many nodes the arena holds, across all its trees
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.node(id).is_some()
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.node(id).map(|node| &node.value)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.node_mut(id).map(|node| &mut node.value)
+    }
+
+    fn node(&self, id: NodeId) -> Option<&ArenaNode<T>> {
+        let slot = self.slots.get(id.index as usize)?;
+        if slot.generation == id.generation { slot.node.as_ref() } else { None }
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> Option<&mut ArenaNode<T>> {
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation == id.generation { slot.node.as_mut() } else { None }
+    }
+
+    // for ids handed in from outside, which may have been removed
+    fn live(&self, id: NodeId) -> &ArenaNode<T> {
+        self.node(id).unwrap_or_else(|| removed(id))
+    }
+
+    fn live_mut(&mut self, id: NodeId) -> &mut ArenaNode<T> {
+        self.node_mut(id).unwrap_or_else(|| removed(id))
+    }
+
+    // for links, which never point at a removed node
+    fn at(&self, index: u32) -> &ArenaNode<T> {
+        self.slots[index as usize].node.as_ref().unwrap()
+    }
+
+    fn at_mut(&mut self, index: u32) -> &mut ArenaNode<T> {
+        self.slots[index as usize].node.as_mut().unwrap()
+    }
+
+    fn id(&self, index: u32) -> NodeId {
+        NodeId { index, generation: self.slots[index as usize].generation }
+    }
+
+    // Makes `child` the last child of `parent`, taking it away from its old
+    // parent if it had one. Panics if `child` is `parent` or one of its
+    // ancestors, as for `Node::add_child`.
+    pub fn add_child(&mut self, parent: NodeId, child: NodeId) {
+        self.live(parent);
+        assert!(parent != child && !self.ancestors(parent).any(|a| a == child),
+                "a node can't become a child of itself or of its descendants");
+
+        self.detach(child);
+        let (parent, child) = (parent.index, child.index);
+        let last = self.at(parent).last_child;
+        let node = self.at_mut(child);
+        node.parent = Some(parent);
+        node.prev_sibling = last;
+        match last {
+            Some(last) => self.at_mut(last).next_sibling = Some(child),
+            None => self.at_mut(parent).first_child = Some(child),
+        }
+        self.at_mut(parent).last_child = Some(child);
+    }
+
+    // returns whether `child` was a child of `parent`
+    pub fn remove_child(&mut self, parent: NodeId, child: NodeId) -> bool {
+        if self.parent(child) != Some(parent) {
+            return false;
+        }
+        self.detach(child);
+        true
+    }
+
+    // takes `id`, and everything under it, out of its parent
+    pub fn detach(&mut self, id: NodeId) {
+        self.live(id);
+        let node = self.at_mut(id.index);
+        let Some(parent) = node.parent.take() else {
+            return;
+        };
+        let (prev, next) = (node.prev_sibling.take(), node.next_sibling.take());
+        match prev {
+            Some(prev) => self.at_mut(prev).next_sibling = next,
+            None => self.at_mut(parent).first_child = next,
+        }
+        match next {
+            Some(next) => self.at_mut(next).prev_sibling = prev,
+            None => self.at_mut(parent).last_child = prev,
+        }
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id)?.parent.map(|parent| self.id(parent))
+    }
+
+    pub fn children(&self, id: NodeId) -> ArenaChildren<'_, T> {
+        ArenaChildren { tree: self, next: self.node(id).and_then(|node| node.first_child) }
+    }
+
+    pub fn is_leaf(&self, id: NodeId) -> bool {
+        self.node(id).is_none_or(|node| node.first_child.is_none())
+    }
+
+    // the parent, its parent, and so on up to the root
+    pub fn ancestors(&self, id: NodeId) -> ArenaAncestors<'_, T> {
+        ArenaAncestors { tree: self, next: self.node(id).and_then(|node| node.parent) }
+    }
+
+    // how many ancestors `id` has; a root is at depth 0
+    pub fn depth(&self, id: NodeId) -> usize {
+        self.ancestors(id).count()
+    }
+
+    pub fn root(&self, id: NodeId) -> NodeId {
+        self.ancestors(id).last().unwrap_or(id)
+    }
+
+    // `id`, then each child's subtree in turn
+    pub fn preorder(&self, id: NodeId) -> ArenaPreorder<'_, T> {
+        ArenaPreorder { tree: self, start: id.index, next: self.node(id).map(|_| id.index) }
+    }
+
+    // each child's subtree in turn, then `id`
+    pub fn postorder(&self, id: NodeId) -> ArenaPostorder<'_, T> {
+        ArenaPostorder { tree: self, start: id.index, next: self.node(id).map(|_| self.first_leaf(id.index)) }
+    }
+
+    // `id`, then its children, then their children, and so on
+    pub fn breadth_first(&self, id: NodeId) -> ArenaBreadthFirst<'_, T> {
+        ArenaBreadthFirst { tree: self, queue: self.node(id).map(|_| id.index).into_iter().collect() }
+    }
+
+    // the first leaf under `index`, following first children down
+    fn first_leaf(&self, mut index: u32) -> u32 {
+        while let Some(child) = self.at(index).first_child {
+            index = child;
+        }
+        index
+    }
+}
+
+impl<T> Default for ArenaTree<T> {
+    fn default() -> ArenaTree<T> {
+        ArenaTree::new()
+    }
+}
+
+// the panic for using an id whose node is gone
+fn removed(id: NodeId) -> ! {
+    panic!("{:?} has been removed from the arena", id)
+}
+
+impl<T> Index<NodeId> for ArenaTree<T> {
+    type Output = T;
+
+    fn index(&self, id: NodeId) -> &T {
+        &self.live(id).value
+    }
+}
+
+impl<T> IndexMut<NodeId> for ArenaTree<T> {
+    fn index_mut(&mut self, id: NodeId) -> &mut T {
+        &mut self.live_mut(id).value
+    }
+}
+
+pub struct ArenaChildren<'a, T> {
+    tree: &'a ArenaTree<T>,
+    next: Option<u32>,
+}
+
+impl<T> Iterator for ArenaChildren<'_, T> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let index = self.next?;
+        self.next = self.tree.at(index).next_sibling;
+        Some(self.tree.id(index))
+    }
+}
+
+pub struct ArenaAncestors<'a, T> {
+    tree: &'a ArenaTree<T>,
+    next: Option<u32>,
+}
+
+impl<T> Iterator for ArenaAncestors<'_, T> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let index = self.next?;
+        self.next = self.tree.at(index).parent;
+        Some(self.tree.id(index))
+    }
+}
+
+// Preorder and postorder find their way with the sibling and parent links
+// alone, so unlike the `Rc` walks they need no stack.
+pub struct ArenaPreorder<'a, T> {
+    tree: &'a ArenaTree<T>,
+    start: u32,
+    next: Option<u32>,
+}
+
+impl<T> Iterator for ArenaPreorder<'_, T> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let index = self.next?;
+        self.next = self.tree.at(index).first_child.or_else(|| {
+            // the next sibling of this node or of the nearest ancestor with
+            // one, short of leaving the subtree
+            let mut at = index;
+            while at != self.start {
+                let node = self.tree.at(at);
+                if node.next_sibling.is_some() {
+                    return node.next_sibling;
+                }
+                at = node.parent?;
+            }
+            None
+        });
+        Some(self.tree.id(index))
+    }
+}
+
+pub struct ArenaPostorder<'a, T> {
+    tree: &'a ArenaTree<T>,
+    start: u32,
+    next: Option<u32>,
+}
+
+impl<T> Iterator for ArenaPostorder<'_, T> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let index = self.next?;
+        self.next = if index == self.start {
+            None
+        } else {
+            let node = self.tree.at(index);
+            match node.next_sibling {
+                Some(sibling) => Some(self.tree.first_leaf(sibling)),
+                None => node.parent,
+            }
+        };
+        Some(self.tree.id(index))
+    }
+}
+
+pub struct ArenaBreadthFirst<'a, T> {
+    tree: &'a ArenaTree<T>,
+    queue: VecDeque<u32>,
+}
+
+impl<T> Iterator for ArenaBreadthFirst<'_, T> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let index = self.queue.pop_front()?;
+        let mut child = self.tree.at(index).first_child;
+        while let Some(next) = child {
+            self.queue.push_back(next);
+            child = self.tree.at(next).next_sibling;
+        }
+        Some(self.tree.id(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //       1
+    //     /   \
+    //    2     5
+    //   / \     \
+    //  3   4     6
+    fn sample() -> (ArenaTree<i32>, Vec<NodeId>) {
+        let mut tree = ArenaTree::new();
+        let ids: Vec<NodeId> = (1..=6).map(|v| tree.insert(v)).collect();
+        tree.add_child(ids[0], ids[1]);
+        tree.add_child(ids[1], ids[2]);
+        tree.add_child(ids[1], ids[3]);
+        tree.add_child(ids[0], ids[4]);
+        tree.add_child(ids[4], ids[5]);
+        (tree, ids)
+    }
+
+    fn values<I: Iterator<Item = NodeId>>(tree: &ArenaTree<i32>, ids: I) -> Vec<i32> {
+        ids.map(|id| tree[id]).collect()
+    }
+
+    #[test]
+    fn traversals() {
+        let (tree, ids) = sample();
+        assert_eq!(values(&tree, tree.preorder(ids[0])), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(values(&tree, tree.postorder(ids[0])), vec![3, 4, 2, 6, 5, 1]);
+        assert_eq!(values(&tree, tree.breadth_first(ids[0])), vec![1, 2, 5, 3, 4, 6]);
+        assert_eq!(values(&tree, tree.children(ids[1])), vec![3, 4]);
+
+        // a subtree's walks stop at its own root
+        assert_eq!(values(&tree, tree.preorder(ids[1])), vec![2, 3, 4]);
+        assert_eq!(values(&tree, tree.postorder(ids[1])), vec![3, 4, 2]);
+        assert_eq!(values(&tree, tree.preorder(ids[3])), vec![4]);
+    }
+
+    #[test]
+    fn ancestors_depth_and_root() {
+        let (tree, ids) = sample();
+        assert_eq!(values(&tree, tree.ancestors(ids[3])), vec![2, 1]);
+        assert_eq!(tree.depth(ids[3]), 2);
+        assert_eq!(tree.depth(ids[0]), 0);
+        assert_eq!(tree.root(ids[5]), ids[0]);
+        assert_eq!(tree.root(ids[0]), ids[0]);
+        assert!(tree.is_leaf(ids[2]));
+        assert!(!tree.is_leaf(ids[1]));
+    }
+
+    #[test]
+    fn moving_a_subtree() {
+        let (mut tree, ids) = sample();
+        tree.add_child(ids[5], ids[1]);
+
+        assert_eq!(values(&tree, tree.preorder(ids[0])), vec![1, 5, 6, 2, 3, 4]);
+        assert_eq!(tree.depth(ids[3]), 4);
+    }
+
+    #[test]
+    fn removing_and_detaching() {
+        let (mut tree, ids) = sample();
+
+        assert!(tree.remove_child(ids[0], ids[1]));
+        assert!(!tree.remove_child(ids[0], ids[1]));
+        assert_eq!(tree.parent(ids[1]), None);
+        assert_eq!(values(&tree, tree.preorder(ids[0])), vec![1, 5, 6]);
+
+        // a detached node keeps its own subtree, and stays in the arena
+        tree.detach(ids[4]);
+        assert_eq!(values(&tree, tree.preorder(ids[4])), vec![5, 6]);
+        assert_eq!(values(&tree, tree.children(ids[0])), Vec::<i32>::new());
+        assert_eq!(tree.len(), 6);
+    }
+
+    #[test]
+    fn removing_a_subtree_retires_its_ids() {
+        let (mut tree, ids) = sample();
+
+        assert_eq!(tree.remove(ids[1]), Some(2));
+        assert_eq!(tree.len(), 3);
+        assert_eq!(values(&tree, tree.preorder(ids[0])), vec![1, 5, 6]);
+        for &gone in &ids[1..4] {
+            assert!(!tree.contains(gone));
+            assert_eq!(tree.get(gone), None);
+            assert_eq!(tree.preorder(gone).count(), 0);
+        }
+        assert_eq!(tree.remove(ids[1]), None);
+
+        // new nodes reuse the slots, but not the ids
+        let fresh = tree.insert(7);
+        assert!(ids[1..4].iter().all(|&gone| gone != fresh));
+        assert_eq!(tree.get(ids[2]), None);
+        assert_eq!(tree[fresh], 7);
+    }
+
+    #[test]
+    fn values_can_be_changed_in_place() {
+        let (mut tree, ids) = sample();
+        tree[ids[2]] *= 10;
+        *tree.get_mut(ids[3]).unwrap() += 1;
+        assert_eq!(values(&tree, tree.children(ids[1])), vec![30, 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "descendants")]
+    fn refuses_to_make_a_cycle() {
+        let (mut tree, ids) = sample();
+        tree.add_child(ids[3], ids[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "removed from the arena")]
+    fn stale_ids_cant_be_indexed() {
+        let (mut tree, ids) = sample();
+        tree.remove(ids[5]);
+        let _ = tree[ids[5]];
+    }
+}
@@ -0,0 +1,392 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+// Anything that holds strong references to more values of its own type. The
+// cycle finder only follows these links; weak ones can't keep anything alive.
+pub trait StrongLinks: Sized {
+    fn strong_links(&self) -> Vec<Rc<Self>>;
+
+    // a short name for the value in reports, without its links
+    fn label(&self) -> String;
+
+    // links that don't own their target, like a tree node's parent; only
+    // drawn by `to_dot`
+    fn weak_links(&self) -> Vec<Weak<Self>> {
+        vec![]
+    }
+}
+
+// A ring of strong references: each value holds the next, and the last holds
+// the first again. Nothing in a cycle is ever freed, even after every other
+// handle to it is dropped, unless one of its links is replaced.
+pub struct Cycle<G> {
+    pub path: Vec<Rc<G>>,
+}
+
+impl<G: StrongLinks> fmt::Display for Cycle<G> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for node in &self.path {
+            write!(f, "{} -> ", node.label())?;
+        }
+        match self.path.first() {
+            Some(first) => write!(f, "{}", first.label()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<G: StrongLinks> fmt::Debug for Cycle<G> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cycle({})", self)
+    }
+}
+
+// Every elementary strong cycle reachable from `start`: each ring of values
+// that passes through no value twice, however many of them share values. Each
+// is reported once, from the first of its values the walk reached; they come
+// in that order, and those from the same value in the order the walk found
+// them. A densely linked graph can have a great many. Everything here keeps
+// its own stacks, so long chains don't overflow the real one.
+pub fn find_cycles<G: StrongLinks>(start: &Rc<G>) -> Vec<Cycle<G>> {
+    let (nodes, links) = reachable(start);
+
+    // Johnson's algorithm: the cycles through the first value of a strongly
+    // connected component stay within it; after that the value is left out,
+    // and what remains of the component is split up and searched again.
+    let mut component = vec![0; nodes.len()];
+    let mut work = vec![];
+    let mut ids = 0;
+    split((0..nodes.len()).collect(), 0, &links, &mut component, &mut work, &mut ids);
+
+    let mut found = vec![];
+    while let Some((id, vertices)) = work.pop() {
+        let first = *vertices.iter().min().unwrap();
+        circuits(first, &links, |w| component[w] == id, &mut found);
+        component[first] = usize::MAX;
+        let rest = vertices.into_iter().filter(|v| *v != first).collect();
+        split(rest, id, &links, &mut component, &mut work, &mut ids);
+    }
+
+    found.sort_by_key(|cycle| cycle[0]);
+    found
+        .into_iter()
+        .map(|cycle| Cycle { path: cycle.into_iter().map(|v| Rc::clone(&nodes[v])).collect() })
+        .collect()
+}
+
+// Splits the `vertices` of component `id` into their strongly connected
+// components, queueing each one that has a cycle under the next of the `ids`
+// and marking the rest as in none.
+fn split(
+    vertices: Vec<usize>,
+    id: usize,
+    links: &[Vec<usize>],
+    component: &mut [usize],
+    work: &mut Vec<(usize, Vec<usize>)>,
+    ids: &mut usize,
+) {
+    for part in strong_components(&vertices, links, |w| component[w] == id) {
+        let new_id = if part.len() > 1 || links[part[0]].contains(&part[0]) {
+            *ids += 1;
+            work.push((*ids, part.clone()));
+            *ids
+        } else {
+            usize::MAX
+        };
+        for v in part {
+            component[v] = new_id;
+        }
+    }
+}
+
+// Everything reachable from `start`, numbered in the order a depth-first walk
+// reaches it, with each one's strong links as numbers.
+fn reachable<G: StrongLinks>(start: &Rc<G>) -> (Vec<Rc<G>>, Vec<Vec<usize>>) {
+    let mut numbers: HashMap<*const G, usize> = HashMap::new();
+    let mut nodes = vec![];
+    let mut pending = vec![Rc::clone(start)];
+    while let Some(node) = pending.pop() {
+        if numbers.contains_key(&Rc::as_ptr(&node)) {
+            continue;
+        }
+        numbers.insert(Rc::as_ptr(&node), nodes.len());
+        // in reverse, so popping them follows the links in order
+        pending.extend(node.strong_links().into_iter().rev());
+        nodes.push(node);
+    }
+
+    let links = nodes
+        .iter()
+        .map(|node| node.strong_links().iter().map(|link| numbers[&Rc::as_ptr(link)]).collect())
+        .collect();
+    (nodes, links)
+}
+
+// Tarjan's strongly connected components of `vertices`, following only the
+// links to vertices that are `inside`.
+fn strong_components(
+    vertices: &[usize],
+    links: &[Vec<usize>],
+    inside: impl Fn(usize) -> bool,
+) -> Vec<Vec<usize>> {
+    let mut order: HashMap<usize, usize> = HashMap::new();
+    let mut low: HashMap<usize, usize> = HashMap::new();
+    let mut on_stack = HashSet::new();
+    let mut stack = vec![];
+    let mut components = vec![];
+
+    for &root in vertices {
+        if order.contains_key(&root) {
+            continue;
+        }
+        let mut calls = vec![(root, 0)];
+        order.insert(root, order.len());
+        low.insert(root, order[&root]);
+        stack.push(root);
+        on_stack.insert(root);
+
+        while let Some((v, next)) = calls.last_mut() {
+            let v = *v;
+            if let Some(&w) = links[v].get(*next) {
+                *next += 1;
+                if !inside(w) {
+                    continue;
+                }
+                match order.get(&w) {
+                    Some(&seen) => {
+                        if on_stack.contains(&w) {
+                            low.insert(v, low[&v].min(seen));
+                        }
+                    }
+                    None => {
+                        order.insert(w, order.len());
+                        low.insert(w, order[&w]);
+                        stack.push(w);
+                        on_stack.insert(w);
+                        calls.push((w, 0));
+                    }
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low.insert(parent, low[&parent].min(low[&v]));
+            }
+            if low[&v] == order[&v] {
+                let mut component = vec![];
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack.remove(&w);
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+// Johnson's search for every cycle through `first` among the vertices that
+// are `inside`. A vertex stays blocked while no way back to `first` has been
+// found through it, so no dead end is explored twice.
+fn circuits(
+    first: usize,
+    links: &[Vec<usize>],
+    inside: impl Fn(usize) -> bool,
+    found: &mut Vec<Vec<usize>>,
+) {
+    let mut blocked = HashSet::from([first]);
+    let mut blocking: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut path = vec![first];
+    // each vertex on the path, how many of its links it has tried, and
+    // whether any of them led back to `first`
+    let mut calls = vec![(first, 0, false)];
+
+    while let Some((v, next, closed)) = calls.last_mut() {
+        let v = *v;
+        if let Some(&w) = links[v].get(*next) {
+            *next += 1;
+            if w == first {
+                found.push(path.clone());
+                *closed = true;
+            } else if inside(w) && !blocked.contains(&w) {
+                blocked.insert(w);
+                path.push(w);
+                calls.push((w, 0, false));
+            }
+            continue;
+        }
+
+        let closed = *closed;
+        if closed {
+            // unblock `v`, and whatever was waiting on it
+            let mut unblock = vec![v];
+            while let Some(u) = unblock.pop() {
+                if blocked.remove(&u) {
+                    unblock.extend(blocking.remove(&u).unwrap_or_default());
+                }
+            }
+        } else {
+            for &w in &links[v] {
+                if inside(w) || w == first {
+                    let waiting = blocking.entry(w).or_default();
+                    if !waiting.contains(&v) {
+                        waiting.push(v);
+                    }
+                }
+            }
+        }
+        calls.pop();
+        path.pop();
+        if let Some((_, _, parent_closed)) = calls.last_mut() {
+            *parent_closed |= closed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::List::{Cons, Nil};
+    use crate::{List, Node};
+    use std::cell::RefCell;
+
+    fn cons(value: i32, tail: &Rc<List>) -> Rc<List> {
+        Rc::new(Cons(value, RefCell::new(Rc::clone(tail))))
+    }
+
+    fn point(list: &Rc<List>, at: &Rc<List>) {
+        *list.tail().unwrap().borrow_mut() = Rc::clone(at);
+    }
+
+    #[test]
+    fn a_plain_list_has_no_cycles() {
+        let list = cons(1, &cons(2, &Rc::new(Nil)));
+        assert!(find_cycles(&list).is_empty());
+    }
+
+    #[test]
+    fn finds_the_book_cycle() {
+        let a = cons(10, &Rc::new(Nil));
+        let b = cons(9, &a);
+        point(&a, &b);
+
+        let cycles = find_cycles(&a);
+        assert_eq!(cycles.len(), 1);
+        assert!(Rc::ptr_eq(&cycles[0].path[0], &a));
+        assert_eq!(cycles[0].to_string(), "Cons(10) -> Cons(9) -> Cons(10)");
+
+        // the cycle needn't start where the walk does
+        let c = cons(8, &b);
+        assert_eq!(find_cycles(&c)[0].to_string(), "Cons(9) -> Cons(10) -> Cons(9)");
+
+        point(&a, &Rc::new(Nil));
+    }
+
+    #[test]
+    fn finds_a_self_loop() {
+        let a = cons(1, &Rc::new(Nil));
+        point(&a, &a);
+
+        assert_eq!(find_cycles(&a)[0].to_string(), "Cons(1) -> Cons(1)");
+        point(&a, &Rc::new(Nil));
+    }
+
+    #[test]
+    fn walks_long_lists_without_recursing() {
+        let first = cons(0, &Rc::new(Nil));
+        let mut list = Rc::clone(&first);
+        for i in 1..100_000 {
+            list = cons(i, &list);
+        }
+        assert!(find_cycles(&list).is_empty());
+
+        point(&first, &list);
+        assert_eq!(find_cycles(&list)[0].path.len(), 100_000);
+        point(&first, &Rc::new(Nil));
+    }
+
+    // a graph whose values can link to any number of others
+    struct Vertex {
+        name: &'static str,
+        links: RefCell<Vec<Rc<Vertex>>>,
+    }
+
+    impl StrongLinks for Vertex {
+        fn strong_links(&self) -> Vec<Rc<Vertex>> {
+            self.links.borrow().clone()
+        }
+
+        fn label(&self) -> String {
+            String::from(self.name)
+        }
+    }
+
+    fn vertices(names: &[&'static str]) -> Vec<Rc<Vertex>> {
+        names.iter().map(|&name| Rc::new(Vertex { name, links: RefCell::new(vec![]) })).collect()
+    }
+
+    fn link(from: &Rc<Vertex>, to: &[&Rc<Vertex>]) {
+        from.links.borrow_mut().extend(to.iter().map(|v| Rc::clone(v)));
+    }
+
+    fn names(cycles: &[Cycle<Vertex>]) -> Vec<String> {
+        cycles.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_cycles_that_share_values() {
+        let v = vertices(&["A", "B", "C", "D"]);
+        let [a, b, c, d] = [&v[0], &v[1], &v[2], &v[3]];
+        link(a, &[b, c]);
+        link(b, &[d]);
+        link(c, &[d]);
+        link(d, &[a]);
+
+        // the second cycle closes through D, which the walk has already finished
+        assert_eq!(names(&find_cycles(a)), vec!["A -> B -> D -> A", "A -> C -> D -> A"]);
+        assert_eq!(names(&find_cycles(d)), vec!["D -> A -> B -> D", "D -> A -> C -> D"]);
+
+        for vertex in &v {
+            vertex.links.borrow_mut().clear();
+        }
+    }
+
+    #[test]
+    fn finds_every_elementary_cycle() {
+        let v = vertices(&["A", "B", "C"]);
+        let [a, b, c] = [&v[0], &v[1], &v[2]];
+        link(a, &[b, c]);
+        link(b, &[a, c]);
+        link(c, &[a, b, c]);
+
+        assert_eq!(names(&find_cycles(a)), vec![
+            "A -> B -> A",
+            "A -> B -> C -> A",
+            "A -> C -> A",
+            "A -> C -> B -> A",
+            "B -> C -> B",
+            "C -> C",
+        ]);
+
+        for vertex in &v {
+            vertex.links.borrow_mut().clear();
+        }
+    }
+
+    #[test]
+    fn trees_have_no_strong_cycles() {
+        let root = Node::new(1);
+        let child = Node::new(2);
+        root.add_child(Rc::clone(&child));
+        child.add_child(Node::new(3));
+
+        assert!(find_cycles(&root).is_empty());
+        assert!(find_cycles(&child).is_empty());
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::mem;
+use std::rc::Rc;
+
+use serde::de::{self, DeserializeOwned, IgnoredAny};
+use serde::Serialize;
+
+use crate::cycles::StrongLinks;
+use crate::tree::{Node, Tree};
+
+// Renders everything reachable from `start` as a Graphviz digraph. Strong
+// links are solid edges and weak ones dashed; each value is labelled with its
+// `Rc::strong_count` and `Rc::weak_count`, not counting the references this
+// function holds while it works. Cycles are drawn like any other edge.
+pub fn to_dot<G: StrongLinks>(start: &Rc<G>) -> String {
+    let mut ids: HashMap<*const G, usize> = HashMap::new();
+    let mut nodes: Vec<Rc<G>> = vec![];
+    let mut strong_edges = vec![];
+    let mut weak_edges = vec![];
+
+    ids.insert(Rc::as_ptr(start), 0);
+    nodes.push(Rc::clone(start));
+    let mut next = 0;
+    while next < nodes.len() {
+        for link in nodes[next].strong_links() {
+            let id = *ids.entry(Rc::as_ptr(&link)).or_insert_with(|| {
+                nodes.push(Rc::clone(&link));
+                nodes.len() - 1
+            });
+            strong_edges.push((next, id));
+        }
+        next += 1;
+    }
+
+    // weak links are only drawn between values already found, so a subtree
+    // is drawn without the rest of the tree around it
+    for (from, node) in nodes.iter().enumerate() {
+        for link in node.weak_links() {
+            if let Some(to) = ids.get(&link.as_ptr()) {
+                weak_edges.push((from, *to));
+            }
+        }
+    }
+
+    let mut dot = String::from("digraph {\n");
+    for (id, node) in nodes.iter().enumerate() {
+        let label = format!("{}\nstrong={} weak={}", node.label(), Rc::strong_count(node) - 1, Rc::weak_count(node));
+        writeln!(dot, "    n{} [label=\"{}\"];", id, escape(&label)).unwrap();
+    }
+    for (from, to) in strong_edges {
+        writeln!(dot, "    n{} -> n{};", from, to).unwrap();
+    }
+    for (from, to) in weak_edges {
+        writeln!(dot, "    n{} -> n{} [style=dashed];", from, to).unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Writes the tree as nested `{ "value": ..., "children": [...] }` objects.
+// Parent links aren't written; `tree_from_json` restores them from the
+// nesting. Like `Debug`, it keeps its own stack, so any depth can be written.
+pub fn tree_to_json<T: Serialize>(root: &Tree<T>) -> serde_json::Result<String> {
+    enum Step<T> {
+        Open(Tree<T>),
+        Comma,
+        Close,
+    }
+
+    let mut json = String::new();
+    let mut steps = vec![Step::Open(Rc::clone(root))];
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Open(node) => {
+                json.push_str("{\"value\":");
+                json.push_str(&serde_json::to_string(&node.value)?);
+                json.push_str(",\"children\":[");
+                steps.push(Step::Close);
+                for (i, child) in node.children().into_iter().enumerate().rev() {
+                    steps.push(Step::Open(child));
+                    if i > 0 {
+                        steps.push(Step::Comma);
+                    }
+                }
+            }
+            Step::Comma => json.push(','),
+            Step::Close => json.push_str("]}"),
+        }
+    }
+    Ok(json)
+}
+
+// Rebuilds a tree written by `tree_to_json`, with every child owned by its
+// parent and pointing back at it weakly. The nesting is read with a stack of
+// its own, so it's as deep as the tree needs; only each value goes through
+// serde_json, which refuses one nested more than 128 levels.
+pub fn tree_from_json<T: DeserializeOwned>(json: &str) -> serde_json::Result<Tree<T>> {
+    enum Frame {
+        Object { node: usize, first: bool },
+        Children { node: usize, first: bool },
+    }
+
+    let mut reader = Reader { json, at: 0 };
+    // every node's value and children, parents before their children
+    let mut values: Vec<Option<T>> = vec![None];
+    let mut children: Vec<Vec<usize>> = vec![vec![]];
+    let mut frames = vec![Frame::Object { node: 0, first: true }];
+    reader.expect(b'{')?;
+
+    while let Some(frame) = frames.last_mut() {
+        match frame {
+            Frame::Object { node, first } => {
+                let node = *node;
+                if reader.next_is(b'}') {
+                    if values[node].is_none() {
+                        return Err(reader.error("missing field `value`"));
+                    }
+                    frames.pop();
+                    continue;
+                }
+                if !mem::replace(first, false) {
+                    reader.expect(b',')?;
+                }
+                reader.skip_whitespace();
+                let field = reader.at;
+                let key: String = reader.value()?;
+                reader.expect(b':')?;
+                match key.as_str() {
+                    "value" if values[node].is_none() => values[node] = Some(reader.value()?),
+                    "children" => {
+                        reader.expect(b'[')?;
+                        frames.push(Frame::Children { node, first: true });
+                    }
+                    "value" => return Err(error_at(field, "duplicate field `value`")),
+                    _ => {
+                        reader.value::<IgnoredAny>()?;
+                    }
+                }
+            }
+            Frame::Children { node, first } => {
+                let node = *node;
+                if reader.next_is(b']') {
+                    frames.pop();
+                    continue;
+                }
+                if !mem::replace(first, false) {
+                    reader.expect(b',')?;
+                }
+                reader.expect(b'{')?;
+                let child = values.len();
+                values.push(None);
+                children.push(vec![]);
+                children[node].push(child);
+                frames.push(Frame::Object { node: child, first: true });
+            }
+        }
+    }
+    reader.end()?;
+
+    let nodes: Vec<Tree<T>> = values.into_iter().map(|value| Node::new(value.unwrap())).collect();
+    for (parent, children) in nodes.iter().zip(children) {
+        for child in children {
+            parent.push_child(Rc::clone(&nodes[child]));
+        }
+    }
+    Ok(Rc::clone(&nodes[0]))
+}
+
+// Steps through JSON text a token at a time, for `tree_from_json`.
+struct Reader<'a> {
+    json: &'a str,
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.json[self.at..];
+        self.at += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    // skips `byte` if it comes next
+    fn next_is(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.json.as_bytes().get(self.at) == Some(&byte);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> serde_json::Result<()> {
+        if self.next_is(byte) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", byte as char)))
+        }
+    }
+
+    // one whole JSON value, read by serde_json
+    fn value<V: DeserializeOwned>(&mut self) -> serde_json::Result<V> {
+        self.skip_whitespace();
+        let mut values = serde_json::Deserializer::from_str(&self.json[self.at..]).into_iter::<V>();
+        let value = values.next().unwrap_or_else(|| Err(self.error("unexpected end of input")))?;
+        self.at += values.byte_offset();
+        Ok(value)
+    }
+
+    fn end(&mut self) -> serde_json::Result<()> {
+        self.skip_whitespace();
+        if self.at == self.json.len() {
+            Ok(())
+        } else {
+            Err(self.error("trailing characters"))
+        }
+    }
+
+    fn error(&self, message: &str) -> serde_json::Error {
+        error_at(self.at, message)
+    }
+}
+
+fn error_at(at: usize, message: &str) -> serde_json::Error {
+    de::Error::custom(format_args!("{} at byte {}", message, at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::List::{self, Cons, Nil};
+    use std::cell::RefCell;
+
+    fn sample() -> Tree<&'static str> {
+        let root = Node::new("root");
+        let branch = Node::new("branch");
+        root.add_child(Rc::clone(&branch));
+        branch.add_child(Node::new("leaf"));
+        root.add_child(Node::new("other \"leaf\""));
+        root
+    }
+
+    #[test]
+    fn draws_a_tree() {
+        let root = sample();
+        assert_eq!(to_dot(&root), "digraph {
+    n0 [label=\"Node(\\\"root\\\")\\nstrong=1 weak=2\"];
+    n1 [label=\"Node(\\\"branch\\\")\\nstrong=1 weak=1\"];
+    n2 [label=\"Node(\\\"other \\\\\\\"leaf\\\\\\\"\\\")\\nstrong=1 weak=0\"];
+    n3 [label=\"Node(\\\"leaf\\\")\\nstrong=1 weak=0\"];
+    n0 -> n1;
+    n0 -> n2;
+    n1 -> n3;
+    n1 -> n0 [style=dashed];
+    n2 -> n0 [style=dashed];
+    n3 -> n1 [style=dashed];
+}
+");
+    }
+
+    #[test]
+    fn draws_a_subtree_without_its_parent() {
+        let root = sample();
+        let branch = &root.children()[0];
+        let dot = to_dot(branch);
+
+        assert!(dot.contains("n0 [label=\"Node(\\\"branch\\\")\\nstrong=2 weak=1\"];"));
+        assert!(dot.contains("n1 -> n0 [style=dashed];"));
+        assert_eq!(dot.matches("->").count(), 2);
+    }
+
+    #[test]
+    fn draws_a_list_cycle() {
+        let a = Rc::new(Cons(10, RefCell::new(Rc::new(Nil))));
+        let b = Rc::new(Cons(9, RefCell::new(Rc::clone(&a))));
+        *a.tail().unwrap().borrow_mut() = Rc::clone(&b);
+
+        assert_eq!(to_dot(&a), "digraph {
+    n0 [label=\"Cons(10)\\nstrong=2 weak=0\"];
+    n1 [label=\"Cons(9)\\nstrong=2 weak=0\"];
+    n0 -> n1;
+    n1 -> n0;
+}
+");
+        *a.tail().unwrap().borrow_mut() = Rc::new(List::Nil);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = tree_to_json(&sample()).unwrap();
+        assert_eq!(json, r#"{"value":"root","children":[{"value":"branch","children":[{"value":"leaf","children":[]}]},{"value":"other \"leaf\"","children":[]}]}"#);
+
+        let root: Tree<String> = tree_from_json(&json).unwrap();
+        assert_eq!(tree_to_json(&root).unwrap(), json);
+
+        let leaf = &root.children()[0].children()[0];
+        assert_eq!(leaf.value, "leaf");
+        let path: Vec<String> = leaf.ancestors().map(|n| n.value.clone()).collect();
+        assert_eq!(path, vec!["branch", "root"]);
+        assert_eq!(Rc::strong_count(leaf), 2);
+        assert_eq!(Rc::weak_count(&root), 2);
+    }
+
+    #[test]
+    fn json_round_trip_of_a_deep_tree() {
+        let root = Node::new(0);
+        let mut last = Rc::clone(&root);
+        for i in 1..100_000 {
+            let next = Node::new(i);
+            last.push_child(Rc::clone(&next));
+            last = next;
+        }
+
+        let json = tree_to_json(&root).unwrap();
+        let copy: Tree<u32> = tree_from_json(&json).unwrap();
+        let leaf = copy.preorder().last().unwrap();
+        assert_eq!(leaf.value, 99_999);
+        assert_eq!(leaf.depth(), 99_999);
+        assert_eq!(tree_to_json(&copy).unwrap(), json);
+    }
+
+    #[test]
+    fn json_rejects_malformed_trees() {
+        let err = |json| tree_from_json::<u32>(json).unwrap_err().to_string();
+        assert_eq!(err(r#"{"value": 1, "value": 2}"#), "duplicate field `value` at byte 13");
+        assert_eq!(err(r#"{"value": 1, "children": [{"value": 2}"#), "expected `,` at byte 38");
+        assert_eq!(err(r#"{"value": 1} 2"#), "trailing characters at byte 13");
+        assert!(tree_from_json::<u32>(r#"{"value": "one"}"#).is_err());
+
+        // other fields are skipped, and the fields can come in any order
+        let root: Tree<u32> = tree_from_json(r#"{"children": [{"value": 2, "id": [1]}], "value": 1}"#).unwrap();
+        assert_eq!(tree_to_json(&root).unwrap(), r#"{"value":1,"children":[{"value":2,"children":[]}]}"#);
+    }
+
+    #[test]
+    fn json_children_are_optional() {
+        let root: Tree<u32> = tree_from_json(r#"{"value": 1, "children": [{"value": 2}]}"#).unwrap();
+        assert_eq!(root.children()[0].value, 2);
+        assert!(tree_from_json::<u32>(r#"{"children": []}"#).is_err());
+    }
+}
@@ -1,15 +1,22 @@
 use crate::List::{Cons, Nil};
-use std::rc::{Rc, Weak};
 use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
 
-#[derive(Debug)]
-pub struct Node {
-    pub value: i32,
-    pub parent: RefCell<Weak<Node>>,
-    pub children: RefCell<Vec<Rc<Node>>>,
-}
+mod arena;
+mod cycles;
+mod export;
+mod sync_tree;
+mod tree;
+
+pub use arena::{ArenaAncestors, ArenaBreadthFirst, ArenaChildren, ArenaPostorder, ArenaPreorder, ArenaTree, NodeId};
+pub use cycles::{find_cycles, Cycle, StrongLinks};
+pub use export::{to_dot, tree_from_json, tree_to_json};
+pub use sync_tree::{SyncAncestors, SyncBreadthFirst, SyncNode, SyncPostorder, SyncPreorder, SyncTree};
+pub use tree::{Ancestors, BreadthFirst, Node, Postorder, Preorder, Tree};
 
-#[derive(Debug)]
 pub enum List {
     Cons(i32, RefCell<Rc<List>>),
     Nil,
@@ -22,4 +29,121 @@ impl List {
             Nil => None,
         }
     }
+
+    // Calls `f` with each value down the list until it reaches `Nil`, or a
+    // value it has already seen. Returns the one it has seen, if any.
+    fn walk<F>(&self, mut f: F) -> Result<Option<i32>, fmt::Error> where F: FnMut(i32) -> fmt::Result {
+        let mut seen = HashSet::new();
+        let mut next = match self {
+            Cons(value, tail) => {
+                f(*value)?;
+                seen.insert(self as *const List);
+                Rc::clone(&tail.borrow())
+            }
+            Nil => return Ok(None),
+        };
+
+        loop {
+            if seen.contains(&Rc::as_ptr(&next)) {
+                return Ok(Some(match *next {
+                    Cons(value, _) => value,
+                    Nil => unreachable!("Nil has no tail to come back through"),
+                }));
+            }
+            next = match &*next {
+                Cons(value, tail) => {
+                    f(*value)?;
+                    seen.insert(Rc::as_ptr(&next));
+                    Rc::clone(&tail.borrow())
+                }
+                Nil => return Ok(None),
+            };
+        }
+    }
+}
+
+// Letting each `Cons` drop its tail would go one call deeper per cell, and
+// a long enough list would overflow the stack. The cells only this list
+// holds are unlinked in a loop instead, stopping at the first shared one.
+impl Drop for List {
+    fn drop(&mut self) {
+        let mut next = match self {
+            Cons(_, tail) => mem::replace(tail.get_mut(), Rc::new(Nil)),
+            Nil => return,
+        };
+        while let Ok(mut list) = Rc::try_unwrap(next) {
+            next = match &mut list {
+                Cons(_, tail) => mem::replace(tail.get_mut(), Rc::new(Nil)),
+                Nil => return,
+            };
+        }
+    }
+}
+
+// Written by hand so that a list that loops back on itself prints where it
+// loops instead of overflowing the stack: `Cons(10, Cons(9, <cycle to 10>))`.
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut opened = 0;
+        let again = self.walk(|value| {
+            opened += 1;
+            write!(f, "Cons({}, ", value)
+        })?;
+        match again {
+            Some(value) => write!(f, "<cycle to {}>", value)?,
+            None => write!(f, "Nil")?,
+        }
+        for _ in 0..opened {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+// `10 -> 9 -> Nil`, or `10 -> 9 -> (back to 10)` for a cycle
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.walk(|value| write!(f, "{} -> ", value))? {
+            Some(value) => write!(f, "(back to {})", value),
+            None => write!(f, "Nil"),
+        }
+    }
+}
+
+impl StrongLinks for List {
+    fn strong_links(&self) -> Vec<Rc<List>> {
+        self.tail().map(|tail| Rc::clone(&tail.borrow())).into_iter().collect()
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Cons(value, _) => format!("Cons({})", value),
+            Nil => String::from("Nil"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_a_list() {
+        let list = Cons(10, RefCell::new(Rc::new(Cons(9, RefCell::new(Rc::new(Nil))))));
+        assert_eq!(format!("{:?}", list), "Cons(10, Cons(9, Nil))");
+        assert_eq!(list.to_string(), "10 -> 9 -> Nil");
+        assert_eq!(Nil.to_string(), "Nil");
+    }
+
+    #[test]
+    fn formats_a_cycle_without_recursing_forever() {
+        let a = Rc::new(Cons(10, RefCell::new(Rc::new(Nil))));
+        let b = Rc::new(Cons(9, RefCell::new(Rc::clone(&a))));
+        *a.tail().unwrap().borrow_mut() = Rc::clone(&b);
+
+        assert_eq!(format!("{:?}", a), "Cons(10, Cons(9, <cycle to 10>))");
+        assert_eq!(b.to_string(), "9 -> 10 -> (back to 9)");
+
+        *a.tail().unwrap().borrow_mut() = Rc::new(Nil);
+    }
 }
@@ -1,29 +1,20 @@
 use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use std::rc::Rc;
 
-use reference_cycle::{List, Node};
+use reference_cycle::{find_cycles, List, Node};
 
 fn main() {
-    let leaf = Rc::new(Node {
-        value: 10,
-        parent: RefCell::new(Weak::new()),
-        children: RefCell::new(vec![]),
-    });
+    let leaf = Node::new(10);
 
     println!("leaf strong = {}\tweak= {}", Rc::strong_count(&leaf), Rc::weak_count(&leaf));
     {
-        let branch = Rc::new(Node {
-            value: 9,
-            parent: RefCell::new(Weak::new()),
-            children: RefCell::new(vec![Rc::clone(&leaf)]),
-        });
-
-        *leaf.parent.borrow_mut() = Rc::downgrade(&branch);
+        let branch = Node::new(9);
+        branch.add_child(Rc::clone(&leaf));
 
         println!("leaf strong= {}\tweak= {}", Rc::strong_count(&leaf), Rc::weak_count(&leaf));
         println!("branch strong= {}\tweak= {}", Rc::strong_count(&branch), Rc::weak_count(&branch));
 
-        println!("leaf parent {:?}", leaf.parent.borrow().upgrade());
+        println!("leaf parent {:?}", leaf.parent());
     }
     println!("leaf strong= {}\tweak= {}", Rc::strong_count(&leaf), Rc::weak_count(&leaf));
 }
@@ -45,7 +36,10 @@ fn _cycling() {
     println!("b rc count after changing a = {}", Rc::strong_count(&b));
     println!("a rc count after changing a = {}", Rc::strong_count(&a));
 
-    // Uncomment the next line to see that we have a cycle;
-    // it will overflow the stack
-    // println!("a next item is {:?}", a.tail());
+    // `Debug` stops where the list comes back round instead of overflowing
+    // the stack, and `find_cycles` shows the loop that leaks `a` and `b`
+    println!("a next item is {:?}", a.tail());
+    for cycle in find_cycles(&a) {
+        println!("strong cycle: {}", cycle);
+    }
 }
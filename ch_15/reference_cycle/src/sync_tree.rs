@@ -0,0 +1,351 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+
+// One lock for the whole process: every change to the shape of every
+// `SyncNode` tree, whatever its `T`, happens under it, so adding, removing
+// and moving nodes never runs in parallel, even in trees that have nothing
+// to do with each other. A move touches up to three nodes, and possibly two
+// trees, and with the checks for cycles made under the same lock, two
+// threads can't each move a node under the other.
+static GLOBAL_STRUCTURE_LOCK: Mutex<()> = Mutex::new(());
+
+// Every lock here is used even after a panic poisoned it: each change made
+// under one is a single assignment, push or removal, so a panic can't leave
+// a link half changed.
+fn structure() -> MutexGuard<'static, ()> {
+    GLOBAL_STRUCTURE_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn read<L>(lock: &RwLock<L>) -> RwLockReadGuard<'_, L> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<L>(lock: &RwLock<L>) -> RwLockWriteGuard<'_, L> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+// `Node` for sharing between threads: `Arc` instead of `Rc`, and a `RwLock`
+// around each link. Readers such as `parent`, `children` and the traversals
+// hold one node's lock at a time and never wait for `GLOBAL_STRUCTURE_LOCK`,
+// so a walk up or down the tree can't deadlock with a change to it. A
+// traversal that runs alongside a move may meet the moving subtree twice or
+// not at all, but going up always ends: parent links never form a loop, even
+// halfway through a move.
+//
+// Limitation: changes go through that one process-wide lock, so threads
+// that each reshape their own tree still wait for one another.
+pub struct SyncNode<T> {
+    pub value: T,
+    parent: RwLock<Weak<SyncNode<T>>>,
+    children: RwLock<Vec<Arc<SyncNode<T>>>>,
+}
+
+pub type SyncTree<T> = Arc<SyncNode<T>>;
+
+impl<T> SyncNode<T> {
+    pub fn new(value: T) -> SyncTree<T> {
+        Arc::new(SyncNode {
+            value,
+            parent: RwLock::new(Weak::new()),
+            children: RwLock::new(vec![]),
+        })
+    }
+
+    // Panics if `child` is this node or one of its ancestors; see
+    // `try_add_child` for when other threads may be moving nodes around.
+    pub fn add_child(self: &Arc<Self>, child: SyncTree<T>) {
+        assert!(self.try_add_child(child), "a node can't become a child of itself or of its descendants");
+    }
+
+    // `add_child`, or `false` if `child` is this node or one of its ancestors
+    // at the time of the call
+    pub fn try_add_child(self: &Arc<Self>, child: SyncTree<T>) -> bool {
+        let _structure = structure();
+        if Arc::ptr_eq(self, &child) || self.ancestors().any(|a| Arc::ptr_eq(&a, &child)) {
+            return false;
+        }
+
+        if let Some(old) = child.parent() {
+            old.unlink(&child);
+        }
+        *write(&child.parent) = Arc::downgrade(self);
+        write(&self.children).push(child);
+        true
+    }
+
+    // returns whether `child` was a child of this node
+    pub fn remove_child(&self, child: &SyncTree<T>) -> bool {
+        let _structure = structure();
+        let removed = self.unlink(child);
+        if removed {
+            *write(&child.parent) = Weak::new();
+        }
+        removed
+    }
+
+    pub fn detach(self: &Arc<Self>) {
+        let _structure = structure();
+        if let Some(parent) = self.parent() {
+            parent.unlink(self);
+            *write(&self.parent) = Weak::new();
+        }
+    }
+
+    // takes `child` out of the children, leaving its parent link alone
+    fn unlink(&self, child: &SyncTree<T>) -> bool {
+        let mut children = write(&self.children);
+        match children.iter().position(|c| Arc::ptr_eq(c, child)) {
+            Some(at) => {
+                children.remove(at);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn parent(&self) -> Option<SyncTree<T>> {
+        read(&self.parent).upgrade()
+    }
+
+    pub fn children(&self) -> Vec<SyncTree<T>> {
+        read(&self.children).clone()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        read(&self.children).is_empty()
+    }
+
+    pub fn ancestors(&self) -> SyncAncestors<T> {
+        SyncAncestors { next: self.parent() }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.ancestors().count()
+    }
+
+    pub fn root(self: &Arc<Self>) -> SyncTree<T> {
+        self.ancestors().last().unwrap_or_else(|| Arc::clone(self))
+    }
+
+    pub fn preorder(self: &Arc<Self>) -> SyncPreorder<T> {
+        SyncPreorder { stack: vec![Arc::clone(self)] }
+    }
+
+    pub fn postorder(self: &Arc<Self>) -> SyncPostorder<T> {
+        SyncPostorder { stack: vec![(Arc::clone(self), false)] }
+    }
+
+    pub fn breadth_first(self: &Arc<Self>) -> SyncBreadthFirst<T> {
+        SyncBreadthFirst { queue: VecDeque::from([Arc::clone(self)]) }
+    }
+}
+
+// as for `Node`, so that a deep tree doesn't overflow the stack
+impl<T> Drop for SyncNode<T> {
+    fn drop(&mut self) {
+        let mut orphans = mem::take(self.children.get_mut().unwrap_or_else(PoisonError::into_inner));
+        while let Some(child) = orphans.pop() {
+            if let Ok(mut child) = Arc::try_unwrap(child) {
+                orphans.append(child.children.get_mut().unwrap_or_else(PoisonError::into_inner));
+            }
+        }
+    }
+}
+
+// Only the value and how many children it has; the subtree may be changing
+// under other threads while it's printed.
+impl<T: fmt::Debug> fmt::Debug for SyncNode<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SyncNode")
+            .field("value", &self.value)
+            .field("children", &read(&self.children).len())
+            .finish()
+    }
+}
+
+pub struct SyncAncestors<T> {
+    next: Option<SyncTree<T>>,
+}
+
+impl<T> Iterator for SyncAncestors<T> {
+    type Item = SyncTree<T>;
+
+    fn next(&mut self) -> Option<SyncTree<T>> {
+        let node = self.next.take()?;
+        self.next = node.parent();
+        Some(node)
+    }
+}
+
+pub struct SyncPreorder<T> {
+    stack: Vec<SyncTree<T>>,
+}
+
+impl<T> Iterator for SyncPreorder<T> {
+    type Item = SyncTree<T>;
+
+    fn next(&mut self) -> Option<SyncTree<T>> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children().into_iter().rev());
+        Some(node)
+    }
+}
+
+pub struct SyncPostorder<T> {
+    stack: Vec<(SyncTree<T>, bool)>,
+}
+
+impl<T> Iterator for SyncPostorder<T> {
+    type Item = SyncTree<T>;
+
+    fn next(&mut self) -> Option<SyncTree<T>> {
+        loop {
+            let (node, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(node);
+            }
+            let children = node.children();
+            self.stack.push((node, true));
+            self.stack.extend(children.into_iter().rev().map(|c| (c, false)));
+        }
+    }
+}
+
+pub struct SyncBreadthFirst<T> {
+    queue: VecDeque<SyncTree<T>>,
+}
+
+impl<T> Iterator for SyncBreadthFirst<T> {
+    type Item = SyncTree<T>;
+
+    fn next(&mut self) -> Option<SyncTree<T>> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children());
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn values<I: Iterator<Item = SyncTree<i32>>>(nodes: I) -> Vec<i32> {
+        nodes.map(|n| n.value).collect()
+    }
+
+    // a cheap xorshift, so every thread makes its own moves
+    fn random(state: &mut u64) -> usize {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state as usize
+    }
+
+    #[test]
+    fn same_api_as_node() {
+        let nodes: Vec<SyncTree<i32>> = (1..=6).map(SyncNode::new).collect();
+        nodes[0].add_child(Arc::clone(&nodes[1]));
+        nodes[1].add_child(Arc::clone(&nodes[2]));
+        nodes[1].add_child(Arc::clone(&nodes[3]));
+        nodes[0].add_child(Arc::clone(&nodes[4]));
+        nodes[4].add_child(Arc::clone(&nodes[5]));
+
+        assert_eq!(values(nodes[0].preorder()), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(values(nodes[0].postorder()), vec![3, 4, 2, 6, 5, 1]);
+        assert_eq!(values(nodes[0].breadth_first()), vec![1, 2, 5, 3, 4, 6]);
+        assert_eq!(values(nodes[3].ancestors()), vec![2, 1]);
+        assert!(Arc::ptr_eq(&nodes[5].root(), &nodes[0]));
+
+        assert!(!nodes[3].try_add_child(Arc::clone(&nodes[0])));
+        assert!(nodes[0].remove_child(&nodes[1]));
+        assert!(nodes[1].parent().is_none());
+        nodes[5].detach();
+        assert_eq!(values(nodes[0].preorder()), vec![1, 5]);
+    }
+
+    #[test]
+    fn a_panic_under_a_lock_doesnt_break_the_tree() {
+        let root = SyncNode::new(0);
+        let child = SyncNode::new(1);
+        root.add_child(Arc::clone(&child));
+
+        let poisoner = Arc::clone(&root);
+        let panicked = thread::spawn(move || {
+            let _children = poisoner.children.write().unwrap();
+            panic!("while holding the children");
+        });
+        assert!(panicked.join().is_err());
+        assert!(root.children.is_poisoned());
+
+        root.add_child(SyncNode::new(2));
+        child.detach();
+        assert_eq!(values(root.preorder()), vec![0, 2]);
+        assert!(child.parent().is_none());
+    }
+
+    #[test]
+    fn concurrent_moves_keep_the_tree_consistent() {
+        const NODES: usize = 64;
+        let root = SyncNode::new(0);
+        let nodes: Vec<SyncTree<i32>> = (1..=NODES as i32).map(SyncNode::new).collect();
+        for node in &nodes {
+            root.add_child(Arc::clone(node));
+        }
+
+        thread::scope(|s| {
+            // movers shuffle nodes under one another
+            for seed in 1..=4u64 {
+                let (root, nodes) = (&root, &nodes);
+                s.spawn(move || {
+                    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                    for _ in 0..2_000 {
+                        let child = &nodes[random(&mut state) % NODES];
+                        let target = match random(&mut state) % 8 {
+                            0 => root,
+                            n => &nodes[(random(&mut state) + n) % NODES],
+                        };
+                        target.try_add_child(Arc::clone(child));
+                    }
+                });
+            }
+
+            // walkers climb and descend at the same time
+            for seed in 5..=6u64 {
+                let (root, nodes) = (&root, &nodes);
+                s.spawn(move || {
+                    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                    for _ in 0..2_000 {
+                        let node = &nodes[random(&mut state) % NODES];
+                        // a moving node still hangs from somewhere under the root
+                        assert!(Arc::ptr_eq(&node.root(), root));
+                        assert!(node.depth() <= NODES);
+                        root.preorder().for_each(drop);
+                    }
+                });
+            }
+        });
+
+        // every node is still in the tree, once, and its parent knows it
+        let mut seen: Vec<i32> = root.preorder().map(|n| n.value).collect();
+        seen.sort();
+        assert_eq!(seen, (0..=NODES as i32).collect::<Vec<_>>());
+        for node in root.preorder() {
+            for child in node.children() {
+                assert!(Arc::ptr_eq(&child.parent().unwrap(), &node));
+            }
+        }
+    }
+
+    #[test]
+    fn trees_can_be_sent_to_other_threads() {
+        let root = SyncNode::new(String::from("root"));
+        let worker = Arc::clone(&root);
+        thread::spawn(move || worker.add_child(SyncNode::new(String::from("leaf")))).join().unwrap();
+
+        assert_eq!(root.children()[0].value, "leaf");
+        assert!(Arc::ptr_eq(&root.children()[0].parent().unwrap(), &root));
+    }
+}
@@ -0,0 +1,391 @@
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::mem;
+use std::rc::{Rc, Weak};
+
+use crate::cycles::StrongLinks;
+
+// A node owns its children and only points weakly at its parent, so a tree
+// never holds itself alive. The links are private; `add_child`,
+// `remove_child` and `detach` keep both directions in step.
+pub struct Node<T> {
+    pub value: T,
+    parent: RefCell<Weak<Node<T>>>,
+    children: RefCell<Vec<Rc<Node<T>>>>,
+}
+
+// a tree is handled through the `Rc` of its root, or of any node in it
+pub type Tree<T> = Rc<Node<T>>;
+
+impl<T> Node<T> {
+    pub fn new(value: T) -> Tree<T> {
+        Rc::new(Node {
+            value,
+            parent: RefCell::new(Weak::new()),
+            children: RefCell::new(vec![]),
+        })
+    }
+
+    // Makes `child` the last child of this node, taking it away from its old
+    // parent if it had one. Panics if `child` is this node or one of its
+    // ancestors, since that would make a cycle of strong references.
+    pub fn add_child(self: &Rc<Self>, child: Tree<T>) {
+        assert!(!Rc::ptr_eq(self, &child) && !self.ancestors().any(|a| Rc::ptr_eq(&a, &child)),
+                "a node can't become a child of itself or of its descendants");
+
+        child.detach();
+        *child.parent.borrow_mut() = Rc::downgrade(self);
+        self.children.borrow_mut().push(child);
+    }
+
+    // `add_child` for a child known to be a fresh root, skipping the checks
+    pub(crate) fn push_child(self: &Rc<Self>, child: Tree<T>) {
+        *child.parent.borrow_mut() = Rc::downgrade(self);
+        self.children.borrow_mut().push(child);
+    }
+
+    // returns whether `child` was a child of this node
+    pub fn remove_child(&self, child: &Tree<T>) -> bool {
+        let mut children = self.children.borrow_mut();
+        match children.iter().position(|c| Rc::ptr_eq(c, child)) {
+            Some(at) => {
+                children.remove(at);
+                *child.parent.borrow_mut() = Weak::new();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // takes this node, and everything under it, out of its parent
+    pub fn detach(self: &Rc<Self>) {
+        if let Some(parent) = self.parent() {
+            parent.remove_child(self);
+        }
+    }
+
+    pub fn parent(&self) -> Option<Tree<T>> {
+        self.parent.borrow().upgrade()
+    }
+
+    pub fn children(&self) -> Vec<Tree<T>> {
+        self.children.borrow().clone()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.children.borrow().is_empty()
+    }
+
+    // the parent, its parent, and so on up to the root
+    pub fn ancestors(&self) -> Ancestors<T> {
+        Ancestors { next: self.parent() }
+    }
+
+    // how many ancestors this node has; a root is at depth 0
+    pub fn depth(&self) -> usize {
+        self.ancestors().count()
+    }
+
+    pub fn root(self: &Rc<Self>) -> Tree<T> {
+        self.ancestors().last().unwrap_or_else(|| Rc::clone(self))
+    }
+
+    // this node, then each child's subtree in turn
+    pub fn preorder(self: &Rc<Self>) -> Preorder<T> {
+        Preorder { stack: vec![Rc::clone(self)] }
+    }
+
+    // each child's subtree in turn, then this node
+    pub fn postorder(self: &Rc<Self>) -> Postorder<T> {
+        Postorder { stack: vec![(Rc::clone(self), false)] }
+    }
+
+    // this node, then its children, then their children, and so on
+    pub fn breadth_first(self: &Rc<Self>) -> BreadthFirst<T> {
+        BreadthFirst { queue: VecDeque::from([Rc::clone(self)]) }
+    }
+}
+
+// Dropping the children one level at a time, instead of letting each node
+// drop its own, keeps a deep tree from overflowing the stack.
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        let mut orphans = mem::take(self.children.get_mut());
+        while let Some(child) = orphans.pop() {
+            // children still owned elsewhere live on, subtree and all
+            if let Ok(mut child) = Rc::try_unwrap(child) {
+                orphans.append(child.children.get_mut());
+            }
+        }
+    }
+}
+
+// `Debug` and `Display` walk the tree with their own stack, so deep trees
+// don't overflow the real one, and mark a node met a second time instead of
+// printing it again.
+impl<T: fmt::Debug> fmt::Debug for Node<T> {
+    // `Node(1, [Node(2), Node(3, [Node(4)])])`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        enum Step<T> {
+            Open(Tree<T>),
+            Comma,
+            Close,
+        }
+
+        let mut seen = HashSet::new();
+        let mut steps = vec![];
+        let mut open = |node: &Node<T>, f: &mut fmt::Formatter, steps: &mut Vec<Step<T>>| {
+            if !seen.insert(node as *const Node<T>) {
+                return write!(f, "<again {:?}>", node.value);
+            }
+            let children = node.children.borrow();
+            if children.is_empty() {
+                return write!(f, "Node({:?})", node.value);
+            }
+            write!(f, "Node({:?}, [", node.value)?;
+            steps.push(Step::Close);
+            for (i, child) in children.iter().enumerate().rev() {
+                steps.push(Step::Open(Rc::clone(child)));
+                if i > 0 {
+                    steps.push(Step::Comma);
+                }
+            }
+            Ok(())
+        };
+
+        open(self, f, &mut steps)?;
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Open(node) => open(&node, f, &mut steps)?,
+                Step::Comma => write!(f, ", ")?,
+                Step::Close => write!(f, "])")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Node<T> {
+    // one line per node, indented two spaces per level
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut seen = HashSet::new();
+        seen.insert(self as *const Node<T>);
+        write!(f, "{}", self.value)?;
+
+        let mut stack: Vec<(Tree<T>, usize)> = self.children.borrow().iter().rev().map(|c| (Rc::clone(c), 1)).collect();
+        while let Some((node, depth)) = stack.pop() {
+            write!(f, "\n{:indent$}{}", "", node.value, indent = depth * 2)?;
+            if !seen.insert(Rc::as_ptr(&node)) {
+                write!(f, " (again)")?;
+                continue;
+            }
+            stack.extend(node.children.borrow().iter().rev().map(|c| (Rc::clone(c), depth + 1)));
+        }
+        Ok(())
+    }
+}
+
+impl<T: fmt::Debug> StrongLinks for Node<T> {
+    fn strong_links(&self) -> Vec<Tree<T>> {
+        self.children()
+    }
+
+    fn label(&self) -> String {
+        format!("Node({:?})", self.value)
+    }
+
+    fn weak_links(&self) -> Vec<Weak<Node<T>>> {
+        vec![self.parent.borrow().clone()]
+    }
+}
+
+pub struct Ancestors<T> {
+    next: Option<Tree<T>>,
+}
+
+impl<T> Iterator for Ancestors<T> {
+    type Item = Tree<T>;
+
+    fn next(&mut self) -> Option<Tree<T>> {
+        let node = self.next.take()?;
+        self.next = node.parent();
+        Some(node)
+    }
+}
+
+// The traversals read a node's children when they reach it, so changes to
+// parts of the tree not yet visited show up in the walk.
+pub struct Preorder<T> {
+    stack: Vec<Tree<T>>,
+}
+
+impl<T> Iterator for Preorder<T> {
+    type Item = Tree<T>;
+
+    fn next(&mut self) -> Option<Tree<T>> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.borrow().iter().rev().cloned());
+        Some(node)
+    }
+}
+
+pub struct Postorder<T> {
+    // each node with whether its children are already on the stack
+    stack: Vec<(Tree<T>, bool)>,
+}
+
+impl<T> Iterator for Postorder<T> {
+    type Item = Tree<T>;
+
+    fn next(&mut self) -> Option<Tree<T>> {
+        loop {
+            let (node, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(node);
+            }
+            let children: Vec<Tree<T>> = node.children();
+            self.stack.push((node, true));
+            self.stack.extend(children.into_iter().rev().map(|c| (c, false)));
+        }
+    }
+}
+
+pub struct BreadthFirst<T> {
+    queue: VecDeque<Tree<T>>,
+}
+
+impl<T> Iterator for BreadthFirst<T> {
+    type Item = Tree<T>;
+
+    fn next(&mut self) -> Option<Tree<T>> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children.borrow().iter().cloned());
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //       1
+    //     /   \
+    //    2     5
+    //   / \     \
+    //  3   4     6
+    fn sample() -> (Tree<i32>, Vec<Tree<i32>>) {
+        let nodes: Vec<Tree<i32>> = (1..=6).map(Node::new).collect();
+        nodes[0].add_child(Rc::clone(&nodes[1]));
+        nodes[1].add_child(Rc::clone(&nodes[2]));
+        nodes[1].add_child(Rc::clone(&nodes[3]));
+        nodes[0].add_child(Rc::clone(&nodes[4]));
+        nodes[4].add_child(Rc::clone(&nodes[5]));
+        (Rc::clone(&nodes[0]), nodes)
+    }
+
+    fn values<I: Iterator<Item = Tree<i32>>>(nodes: I) -> Vec<i32> {
+        nodes.map(|n| n.value).collect()
+    }
+
+    #[test]
+    fn add_child_links_both_ways() {
+        let branch = Node::new("branch");
+        let leaf = Node::new("leaf");
+        branch.add_child(Rc::clone(&leaf));
+
+        assert!(Rc::ptr_eq(&leaf.parent().unwrap(), &branch));
+        assert_eq!(branch.children().len(), 1);
+        assert_eq!(Rc::strong_count(&leaf), 2);
+        assert_eq!(Rc::weak_count(&branch), 1);
+
+        // the parent link is weak, so the branch goes away with its last owner
+        drop(branch);
+        assert!(leaf.parent().is_none());
+    }
+
+    #[test]
+    fn traversals() {
+        let (root, _) = sample();
+        assert_eq!(values(root.preorder()), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(values(root.postorder()), vec![3, 4, 2, 6, 5, 1]);
+        assert_eq!(values(root.breadth_first()), vec![1, 2, 5, 3, 4, 6]);
+    }
+
+    #[test]
+    fn ancestors_depth_and_root() {
+        let (root, nodes) = sample();
+        assert_eq!(values(nodes[3].ancestors()), vec![2, 1]);
+        assert_eq!(nodes[3].depth(), 2);
+        assert_eq!(root.depth(), 0);
+        assert!(Rc::ptr_eq(&nodes[5].root(), &root));
+        assert!(Rc::ptr_eq(&root.root(), &root));
+    }
+
+    #[test]
+    fn moving_a_subtree() {
+        let (root, nodes) = sample();
+        nodes[5].add_child(Rc::clone(&nodes[1]));
+
+        assert_eq!(values(root.preorder()), vec![1, 5, 6, 2, 3, 4]);
+        assert_eq!(nodes[3].depth(), 4);
+    }
+
+    #[test]
+    fn removing_and_detaching() {
+        let (root, nodes) = sample();
+
+        assert!(root.remove_child(&nodes[1]));
+        assert!(!root.remove_child(&nodes[1]));
+        assert!(nodes[1].parent().is_none());
+        assert_eq!(values(nodes[1].preorder()), vec![2, 3, 4]);
+
+        nodes[5].detach();
+        assert!(nodes[4].is_leaf());
+        assert_eq!(values(root.preorder()), vec![1, 5]);
+    }
+
+    #[test]
+    fn formats_a_tree() {
+        let (root, _) = sample();
+        assert_eq!(format!("{:?}", root), "Node(1, [Node(2, [Node(3), Node(4)]), Node(5, [Node(6)])])");
+        assert_eq!(root.to_string(), "1\n  2\n    3\n    4\n  5\n    6");
+    }
+
+    #[test]
+    fn formats_a_deep_tree() {
+        let root = Node::new(0);
+        let mut last = Rc::clone(&root);
+        for i in 1..5_000 {
+            let next = Node::new(i);
+            last.add_child(Rc::clone(&next));
+            last = next;
+        }
+
+        let debug = format!("{:?}", root);
+        assert!(debug.starts_with("Node(0, [Node(1, [Node(2, ["));
+        assert!(debug.contains("[Node(4999)])])"));
+        assert_eq!(root.to_string().lines().count(), 5_000);
+    }
+
+    #[test]
+    fn drops_a_deep_tree_without_recursing() {
+        let root = Node::new(0);
+        let mut last = Rc::clone(&root);
+        for i in 1..1_000_000 {
+            let next = Node::new(i);
+            last.push_child(Rc::clone(&next));
+            last = next;
+        }
+
+        drop(last);
+        drop(root);
+    }
+
+    #[test]
+    #[should_panic(expected = "descendants")]
+    fn refuses_to_make_a_cycle() {
+        let (root, nodes) = sample();
+        nodes[3].add_child(root);
+    }
+}